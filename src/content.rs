@@ -1,9 +1,47 @@
-use crate::row::Row;
-use std::{error, fmt, mem};
+use crate::encoding::{Encoding, LineEnding};
+use crate::error::{self, Error};
+use crate::history::{History, Operation};
+use crate::row::{grapheme_width, Row};
+use crate::search::{MatchCount, Pattern};
+use crate::settings::{FileSettings, Settings};
+use crate::syntax::{Highlight, Syntax};
+use ropey::{Rope, RopeBuilder};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use unicode_segmentation::UnicodeSegmentation;
 
+// max number of rendered rows kept in the cache
+const ROW_CACHE_CAPACITY: usize = 4096;
+// pairs of brackets matched by matching_bracket
+const BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+// rows searched for the matching bracket before giving up
+const BRACKET_SEARCH_ROWS: usize = 1000;
+// limits of counting search matches, larger counts are shown like `1000+`
+const MAX_COUNTED_MATCHES: usize = 1000;
+const MAX_COUNTED_ROWS: usize = 100_000;
+
+// text is kept in a rope, each row is terminated by `\n`.
+// Row (render and highlight) is built lazily when it's accessed.
+// line ending, final newline and encoding of the file are restored on write.
 pub struct Content {
     pub filename: Option<String>,
-    pub rows: Vec<Row>,
+    pub syntax: Option<&'static Syntax>,
+    pub line_ending: LineEnding,
+    // whether the file used both LF and CRLF when it was read
+    pub mixed_line_ending: bool,
+    // whether the last row is terminated by line ending
+    pub final_newline: bool,
+    pub encoding: Encoding,
+    pub bom: bool,
+    // `.editorconfig` and filetype settings of the file
+    pub settings: FileSettings,
+    // line ending when content was saved
+    saved_line_ending: LineEnding,
+    tab_stop: usize,
+    text: Rope,
+    // rendered rows by row idx
+    cache: BTreeMap<usize, Row>,
+    history: History,
 }
 
 #[derive(Debug)]
@@ -13,190 +51,806 @@ pub enum SearchDirection {
     None,
 }
 
-#[derive(Debug)]
-struct Error(String);
+impl Content {
+    pub fn from_text(filename: String, text: &str) -> Self {
+        let mut content = Self::from_lines(filename, text.lines());
+        content.line_ending = LineEnding::detect(text);
+        content.mixed_line_ending = LineEnding::is_mixed(text);
+        content.saved_line_ending = content.line_ending;
+        content.final_newline = text.ends_with('\n');
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        content
     }
-}
-impl error::Error for Error {}
 
-impl Error {
-    pub fn new<T>(msg: T) -> Self
+    // empty content of a file which doesn't exist yet
+    pub fn new_file(filename: String) -> Self {
+        Self::from_lines(filename, "".lines())
+    }
+
+    // decode bytes of a file, detect encoding and BOM
+    pub fn from_bytes(filename: String, bytes: &[u8]) -> Self {
+        let (text, encoding, bom) = Encoding::decode(bytes);
+        let mut content = Self::from_text(filename, &text);
+        content.encoding = encoding;
+        content.bom = bom;
+
+        content
+    }
+
+    fn from_lines<'a, I>(filename: String, lines: I) -> Self
     where
-        T: Into<String>,
+        I: Iterator<Item = &'a str>,
     {
-        Self(msg.into())
-    }
-}
+        let mut builder = RopeBuilder::new();
+        for line in lines {
+            builder.append(line);
+            builder.append("\n");
+        }
 
-impl Content {
-    pub fn new(filename: String, rows: Vec<Row>) -> Self {
-        Self {
+        let mut content = Self {
             filename: Some(filename),
-            rows,
-        }
+            text: builder.finish(),
+            ..Self::default()
+        };
+        content.select_syntax();
+
+        content
     }
 
-    pub fn from_text(filename: String, text: &String) -> Self {
-        let rows = text.lines().map(|l| Row::new(l));
+    // select syntax by filename and highlight all rows
+    pub fn select_syntax(&mut self) {
+        self.syntax = self.filename.as_deref().and_then(Syntax::select);
+        self.cache.clear();
+    }
 
-        Self {
-            filename: Some(filename),
-            rows: rows.collect(),
+    pub fn tab_stop(&self) -> usize {
+        self.tab_stop
+    }
+
+    // rows are rendered again with the new tab stop
+    pub fn set_tab_stop(&mut self, tab_stop: usize) {
+        if tab_stop != self.tab_stop {
+            self.tab_stop = tab_stop;
+            self.cache.clear();
         }
     }
 
-    pub fn row_from_index(&self, n: usize) -> Option<&Row> {
-        self.rows.get(n)
+    pub fn num_rows(&self) -> usize {
+        // rope has an empty line after the last `\n`
+        self.text.len_lines() - 1
     }
 
-    // concat all rows with \n
-    pub fn rows_to_string(&self) -> String {
-        self.rows
-            .iter()
-            .map(|row| row.raw.clone())
-            .collect::<Vec<String>>()
-            .join(&"\n")
+    pub fn row_from_index(&mut self, n: usize) -> Option<&Row> {
+        self.load_row(n);
+        self.cache.get(&n)
     }
 
-    pub fn insert_char(
-        &mut self,
-        row_idx: usize,
-        col_idx: usize,
-        c: char,
-    ) -> Result<(), Box<dyn error::Error>> {
-        if let Some(row) = self.rows.get_mut(row_idx) {
-            row.insert(col_idx, c)?
+    // highlight of the returned row can be overwritten.
+    // it is restored when the row is rendered again.
+    pub fn row_from_index_mut(&mut self, n: usize) -> Option<&mut Row> {
+        self.load_row(n);
+        self.cache.get_mut(&n)
+    }
+
+    // return raw string of the row without rendering
+    pub fn line(&self, n: usize) -> Option<String> {
+        if self.num_rows() <= n {
+            return None;
+        }
+        let line = self.text.line(n);
+
+        Some(line.slice(..line.len_chars() - 1).to_string())
+    }
+
+    // write all rows in the line ending and the encoding of the file,
+    // return the number of written bytes
+    pub fn write_to(&self, out: &mut dyn Write) -> io::Result<usize> {
+        let mut written = 0;
+        if self.bom {
+            let bom = self.encoding.bom();
+            out.write_all(bom)?;
+            written += bom.len();
+        }
+
+        let len = self.text.len_chars();
+        // drop `\n` of the last row
+        let end = if self.final_newline {
+            len
         } else {
-            let row = Row::new(String::from(c));
-            self.rows.push(row);
+            len.saturating_sub(1)
+        };
+        for chunk in self.text.slice(..end).chunks() {
+            written += match self.line_ending {
+                LineEnding::Lf => self.encoding.encode(chunk, out)?,
+                LineEnding::CrLf => self.encoding.encode(&chunk.replace('\n', "\r\n"), out)?,
+            };
         }
 
-        Ok(())
+        Ok(written)
     }
 
-    pub fn insert_new_line(
-        &mut self,
-        row_idx: usize,
-        col_idx: usize,
-    ) -> Result<(), Box<dyn error::Error>> {
-        if let Some(row) = self.rows.get_mut(row_idx) {
-            let (first, second) = row.split(col_idx)?;
-            let _ = mem::replace(row, first);
-            self.rows.insert(row_idx + 1, second);
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.line_ending = line_ending;
+    }
+
+    pub fn insert_char(&mut self, row_idx: usize, col_idx: usize, c: char) -> error::Result<()> {
+        self.history.begin_group();
+        let res = self.insert_char_inner(row_idx, col_idx, c);
+        self.history.end_group();
+
+        res
+    }
+
+    fn insert_char_inner(&mut self, row_idx: usize, col_idx: usize, c: char) -> error::Result<()> {
+        if row_idx < self.num_rows() {
+            self.edit(Operation::Insert {
+                row: row_idx,
+                col: col_idx,
+                text: String::from(c),
+            })
         } else {
-            self.rows.push(Row::new(""));
+            let row = self.num_rows();
+            self.edit(Operation::InsertLine { row })?;
+            self.edit(Operation::Insert {
+                row,
+                col: 0,
+                text: String::from(c),
+            })
         }
+    }
 
-        Ok(())
+    pub fn insert_new_line(&mut self, row_idx: usize, col_idx: usize) -> error::Result<()> {
+        if row_idx < self.num_rows() {
+            self.edit(Operation::SplitLine {
+                row: row_idx,
+                col: col_idx,
+            })
+        } else {
+            self.edit(Operation::InsertLine {
+                row: self.num_rows(),
+            })
+        }
     }
 
+    // delete the char at col idx, cursor is restored to the position on undo
     pub fn delete_char(
         &mut self,
         row_idx: usize,
         col_idx: usize,
-    ) -> Result<(), Box<dyn error::Error>> {
-        if let Some(row) = self.rows.get_mut(row_idx) {
-            row.delete(col_idx)
+        cursor: (usize, usize),
+    ) -> error::Result<()> {
+        if let Some(line) = self.line(row_idx) {
+            // delete whole grapheme cluster not to leave combining marks
+            match line.get(col_idx..).and_then(|s| s.graphemes(true).next()) {
+                Some(g) => self.edit(Operation::Delete {
+                    row: row_idx,
+                    col: col_idx,
+                    text: String::from(g),
+                    cursor,
+                }),
+                None => {
+                    let msg = format!(
+                        "row idx: {}, col idx: {} | char not found.",
+                        row_idx, col_idx
+                    );
+                    Err(Error::Edit(msg))
+                }
+            }
         } else {
             let msg = format!("row idx: {} | row not found.", row_idx);
-            Err(Box::new(Error::new(msg)))
+            Err(Error::Edit(msg))
         }
     }
 
     pub fn concatenate_previous_row(
         &mut self,
         row_idx: usize,
-    ) -> Result<(), Box<dyn error::Error>> {
+        cursor: (usize, usize),
+    ) -> error::Result<()> {
         if row_idx == 0 {
             // case of first row, there is no previous string.
             // do nothing
             Ok(())
-        } else if 0 < row_idx && row_idx < self.rows.len() {
-            let row_string = self.rows.remove(row_idx);
-            if let Some(prev_row) = self.rows.get_mut(row_idx - 1) {
-                prev_row.raw.push_str(&*row_string.raw);
-                prev_row.update_render();
-            }
-            Ok(())
+        } else if 0 < row_idx && row_idx < self.num_rows() {
+            let col = self.row_len(row_idx - 1);
+            self.edit(Operation::JoinLine {
+                row: row_idx,
+                col,
+                cursor,
+            })
         } else {
             let msg = format!("row: {} | row index is out of range", row_idx);
-            Err(Box::new(Error::new(msg)))
+            Err(Error::Edit(msg))
+        }
+    }
+
+    // return text between (row idx, col idx) positions, rows are joined by `\n`.
+    // positions out of the text are moved to the nearest valid ones.
+    pub fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let start = self.clamped_char_index(start.0, start.1);
+        let end = self.clamped_char_index(end.0, end.1);
+
+        self.text.slice(start..end.max(start)).to_string()
+    }
+
+    // delete text between (row idx, col idx) positions as one edit
+    pub fn delete_range(
+        &mut self,
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> error::Result<()> {
+        self.history.begin_group();
+        let res = self.delete_range_inner(start, end);
+        self.history.end_group();
+
+        res
+    }
+
+    fn delete_range_inner(
+        &mut self,
+        (row, col): (usize, usize),
+        (end_row, end_col): (usize, usize),
+    ) -> error::Result<()> {
+        if row == end_row {
+            return self.replace(row, col, end_col, "");
+        }
+
+        // delete the tail of the first row, then delete and join following rows
+        let len = self.row_len(row);
+        self.replace(row, col, len, "")?;
+        for r in row + 1..=end_row.min(self.num_rows().saturating_sub(1)) {
+            let end = if r == end_row {
+                end_col
+            } else {
+                self.row_len(row + 1)
+            };
+            self.replace(row + 1, 0, end, "")?;
+            self.edit(Operation::JoinLine {
+                row: row + 1,
+                col,
+                cursor: (row + 1, 0),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    // insert text which may contain `\n` as one edit.
+    // return (row idx, col idx) of the end of inserted text.
+    pub fn insert_text(
+        &mut self,
+        row_idx: usize,
+        col_idx: usize,
+        text: &str,
+    ) -> error::Result<(usize, usize)> {
+        self.history.begin_group();
+        let res = self.insert_text_inner(row_idx, col_idx, text);
+        self.history.end_group();
+
+        res
+    }
+
+    fn insert_text_inner(
+        &mut self,
+        mut row: usize,
+        mut col: usize,
+        text: &str,
+    ) -> error::Result<(usize, usize)> {
+        if self.num_rows() <= row {
+            row = self.num_rows();
+            col = 0;
+            self.edit(Operation::InsertLine { row })?;
+        }
+
+        for (i, line) in text.split('\n').enumerate() {
+            if 0 < i {
+                self.edit(Operation::SplitLine { row, col })?;
+                row += 1;
+                col = 0;
+            }
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if !line.is_empty() {
+                self.edit(Operation::Insert {
+                    row,
+                    col,
+                    text: line.to_string(),
+                })?;
+                col += line.len();
+            }
+        }
+
+        Ok((row, col))
+    }
+
+    // undo the last edit.
+    // return (row idx, col idx) of cursor before the edit, or None if nothing to undo.
+    pub fn undo(&mut self) -> error::Result<Option<(usize, usize)>> {
+        let ops = match self.history.undo() {
+            Some(ops) => ops,
+            None => return Ok(None),
+        };
+        for op in ops.iter().rev() {
+            self.apply(&op.inverse())?;
+        }
+
+        Ok(ops.first().map(Operation::cursor_before))
+    }
+
+    // redo the last undone edit.
+    // return (row idx, col idx) of cursor after the edit, or None if nothing to redo.
+    pub fn redo(&mut self) -> error::Result<Option<(usize, usize)>> {
+        let ops = match self.history.redo() {
+            Some(ops) => ops,
+            None => return Ok(None),
+        };
+        for op in ops.iter() {
+            self.apply(op)?;
+        }
+
+        Ok(ops.last().map(Operation::cursor_after))
+    }
+
+    // next edit is not merged into the last undo step
+    pub fn break_undo_group(&mut self) {
+        self.history.seal();
+    }
+
+    // edits until end_undo_group are undone as one step
+    pub fn begin_undo_group(&mut self) {
+        self.history.seal();
+        self.history.begin_group();
+    }
+
+    pub fn end_undo_group(&mut self) {
+        self.history.end_group();
+        self.history.seal();
+    }
+
+    // replace start..end of the row with text
+    pub fn replace(
+        &mut self,
+        row_idx: usize,
+        start: usize,
+        end: usize,
+        text: &str,
+    ) -> error::Result<()> {
+        let removed = match self.line(row_idx) {
+            Some(line) => match line.get(start..end) {
+                Some(removed) => removed.to_string(),
+                None => {
+                    let msg = format!(
+                        "row idx: {}, col idx: {}..{} | range not found.",
+                        row_idx, start, end
+                    );
+                    return Err(Error::Edit(msg));
+                }
+            },
+            None => {
+                let msg = format!("row idx: {} | row not found.", row_idx);
+                return Err(Error::Edit(msg));
+            }
+        };
+
+        self.history.begin_group();
+        let mut res = Ok(());
+        if !removed.is_empty() {
+            res = self.edit(Operation::Delete {
+                row: row_idx,
+                col: start,
+                text: removed,
+                cursor: (row_idx, end),
+            });
+        }
+        if res.is_ok() && !text.is_empty() {
+            res = self.edit(Operation::Insert {
+                row: row_idx,
+                col: start,
+                text: text.to_string(),
+            });
+        }
+        self.history.end_group();
+
+        res
+    }
+
+    // apply end_of_line, insert_final_newline and trim_trailing_whitespace of the file settings.
+    // trimming is undone as one step.
+    pub fn apply_save_settings(&mut self) -> error::Result<()> {
+        if let Some(line_ending) = self.settings.end_of_line {
+            self.line_ending = line_ending;
+        }
+        if let Some(final_newline) = self.settings.insert_final_newline {
+            self.final_newline = final_newline;
+        }
+        if self.settings.trim_trailing_whitespace != Some(true) {
+            return Ok(());
+        }
+
+        self.begin_undo_group();
+        let mut res = Ok(());
+        for row in 0..self.num_rows() {
+            let line = self.line(row).unwrap_or_default();
+            let trimmed = line.trim_end().len();
+            if trimmed < line.len() {
+                res = self.replace(row, trimmed, line.len(), "");
+                if res.is_err() {
+                    break;
+                }
+            }
         }
+        self.end_undo_group();
+
+        res
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.history.mark_saved();
+        self.saved_line_ending = self.line_ending;
+    }
+
+    // return true if content differs from the saved state
+    pub fn is_modified(&self) -> bool {
+        self.history.is_modified() || self.line_ending != self.saved_line_ending
+    }
+
+    fn edit(&mut self, op: Operation) -> error::Result<()> {
+        self.apply(&op)?;
+        self.history.record(op);
+
+        Ok(())
+    }
+
+    fn apply(&mut self, op: &Operation) -> error::Result<()> {
+        match op {
+            Operation::Insert { row, col, text } => {
+                let idx = self.char_index(*row, *col)?;
+                self.text.insert(idx, text);
+            }
+            Operation::Delete { row, col, text, .. } => {
+                let start = self.char_index(*row, *col)?;
+                let end = self.char_index(*row, col + text.len())?;
+                self.text.remove(start..end);
+            }
+            Operation::SplitLine { row, col } => {
+                let idx = self.char_index(*row, *col)?;
+                self.text.insert_char(idx, '\n');
+            }
+            Operation::JoinLine { row, .. } => {
+                if *row == 0 || self.num_rows() <= *row {
+                    let msg = format!("row: {} | row index is out of range", row);
+                    return Err(Error::Edit(msg));
+                }
+                // remove `\n` of the previous row
+                let idx = self.text.line_to_char(*row) - 1;
+                self.text.remove(idx..idx + 1);
+            }
+            Operation::InsertLine { row } => {
+                if self.num_rows() < *row {
+                    let msg = format!("row: {} | row index is out of range", row);
+                    return Err(Error::Edit(msg));
+                }
+                let idx = self.text.line_to_char(*row);
+                self.text.insert_char(idx, '\n');
+            }
+            Operation::RemoveLine { row } => {
+                if self.num_rows() <= *row {
+                    let msg = format!("row idx: {} | row not found.", row);
+                    return Err(Error::Edit(msg));
+                }
+                let start = self.text.line_to_char(*row);
+                let end = self.text.line_to_char(row + 1);
+                self.text.remove(start..end);
+            }
+        }
+
+        // rows after the first changed row may be shifted or
+        // change multiline comment state, render them again.
+        let first = match op {
+            Operation::JoinLine { row, .. } => row - 1,
+            Operation::Insert { row, .. }
+            | Operation::Delete { row, .. }
+            | Operation::SplitLine { row, .. }
+            | Operation::InsertLine { row }
+            | Operation::RemoveLine { row } => *row,
+        };
+        self.cache.split_off(&first);
+
+        Ok(())
+    }
+
+    // return char index of text from row idx and col idx (byte offset in the row)
+    fn char_index(&self, row_idx: usize, col_idx: usize) -> error::Result<usize> {
+        if self.num_rows() <= row_idx {
+            let msg = format!("row idx: {} | row not found.", row_idx);
+            return Err(Error::Edit(msg));
+        }
+
+        let line = self.text.line(row_idx);
+        let char_idx = line.byte_to_char(col_idx.min(line.len_bytes()));
+        // index must be on char boundary and must not be after `\n`
+        if self.row_len(row_idx) < col_idx || line.char_to_byte(char_idx) != col_idx {
+            let msg = format!(
+                "row idx: {}, col idx: {} | invalid index.",
+                row_idx, col_idx
+            );
+            return Err(Error::Edit(msg));
+        }
+
+        Ok(self.text.line_to_char(row_idx) + char_idx)
+    }
+
+    // return char index of the nearest position to row idx and col idx.
+    // rows after the last one are at the end of text.
+    fn clamped_char_index(&self, row_idx: usize, col_idx: usize) -> usize {
+        if self.num_rows() <= row_idx {
+            return self.text.len_chars();
+        }
+
+        let line = self.text.line(row_idx);
+        // col idx inside of a char is moved to its start
+        let char_idx = line.byte_to_char(col_idx.min(self.row_len(row_idx)));
+
+        self.text.line_to_char(row_idx) + char_idx
+    }
+
+    // return byte length of the row without `\n`
+    fn row_len(&self, row_idx: usize) -> usize {
+        self.text.line(row_idx).len_bytes().saturating_sub(1)
+    }
+
+    fn load_row(&mut self, n: usize) {
+        if self.num_rows() <= n || self.cache.contains_key(&n) {
+            return;
+        }
+
+        let prev_open_comment = self.open_comment_before(n);
+        let mut row = Row::with_tab_stop(self.line(n).unwrap_or_default(), self.tab_stop);
+        row.prev_open_comment = prev_open_comment;
+        row.set_syntax(self.syntax);
+
+        if ROW_CACHE_CAPACITY <= self.cache.len() {
+            // evict the row farthest from the loaded row
+            let first = self.cache.first_key_value().map_or(n, |(i, _)| *i);
+            let last = self.cache.last_key_value().map_or(n, |(i, _)| *i);
+            if n.abs_diff(first) < n.abs_diff(last) {
+                self.cache.pop_last();
+            } else {
+                self.cache.pop_first();
+            }
+        }
+        self.cache.insert(n, row);
+    }
+
+    // position of the bracket matching the one at pos, skipping strings and comments outside pos
+    pub fn matching_bracket(&mut self, (row, col): (usize, usize)) -> Option<(usize, usize)> {
+        // rows are scanned only if the cursor is on a bracket
+        let c = self.row_from_index(row)?.raw.get(col..)?.chars().next()?;
+        let (other, forward) = BRACKETS.iter().find_map(|&(open, close)| {
+            if c == open {
+                Some((close, true))
+            } else if c == close {
+                Some((open, false))
+            } else {
+                None
+            }
+        })?;
+        let &(.., code) = self.brackets(row).iter().find(|(i, ..)| *i == col)?;
+
+        let rows: Vec<usize> = if forward {
+            (row..self.num_rows().min(row + BRACKET_SEARCH_ROWS)).collect()
+        } else {
+            (row.saturating_sub(BRACKET_SEARCH_ROWS)..=row)
+                .rev()
+                .collect()
+        };
+        let mut depth = 0;
+        for r in rows {
+            let mut brackets = self.brackets(r);
+            if !forward {
+                brackets.reverse();
+            }
+            for (i, b, is_code) in brackets {
+                // only brackets after pos in the search direction
+                if r == row && (forward && i <= col || !forward && col <= i) {
+                    continue;
+                }
+                if code && !is_code {
+                    continue;
+                }
+                if b == c {
+                    depth += 1;
+                } else if b == other {
+                    if depth == 0 {
+                        return Some((r, i));
+                    }
+                    depth -= 1;
+                }
+            }
+        }
+
+        None
+    }
+
+    // (col idx, char, whether out of strings and comments) of brackets in the row
+    fn brackets(&mut self, row: usize) -> Vec<(usize, char, bool)> {
+        let Some(row) = self.row_from_index(row) else {
+            return Vec::new();
+        };
+        let tab_stop = row.tab_stop();
+        let mut brackets = Vec::new();
+        // index of hl is walked along with raw, tab is rendered as spaces
+        let mut render_index = 0;
+        let mut hl_index = 0;
+        for (i, g) in row.raw.grapheme_indices(true) {
+            for (offset, (j, c)) in g.char_indices().enumerate() {
+                if !is_bracket(c) {
+                    continue;
+                }
+                let hl = row.hl.get(hl_index + offset).copied();
+                let code = !matches!(
+                    hl,
+                    Some(Highlight::String | Highlight::Comment | Highlight::MultilineComment)
+                );
+                brackets.push((i + j, c, code));
+            }
+            let width = grapheme_width(g, render_index, tab_stop);
+            hl_index += if g == "\t" { width } else { g.chars().count() };
+            render_index += width;
+        }
+
+        brackets
+    }
+
+    // return whether the row before n ends inside of multiline comment.
+    // rows between the nearest cached row and n are highlighted but not cached.
+    fn open_comment_before(&self, n: usize) -> bool {
+        let syntax = match self.syntax {
+            Some(syntax) if !syntax.multiline_comment_start.is_empty() => syntax,
+            _ => return false,
+        };
+
+        let (start, mut open_comment) = match self.cache.range(..n).next_back() {
+            Some((i, row)) => (i + 1, row.hl_open_comment),
+            None => (0, false),
+        };
+        for i in start..n {
+            let mut row = Row::with_tab_stop(self.line(i).unwrap_or_default(), self.tab_stop);
+            row.prev_open_comment = open_comment;
+            row.set_syntax(Some(syntax));
+            open_comment = row.hl_open_comment;
+        }
+
+        open_comment
     }
 
     pub fn is_phantom(&self) -> bool {
         self.filename.is_none()
     }
 
-    // return (row idx, col idx)
+    // return (row idx, start col idx, end col idx) of the match.
+    // search wraps around at the end (or the start) of the file.
     pub fn find(
         &self,
-        query: &String,
+        pattern: &Pattern,
         row_idx: usize,
         col_idx: usize,
         direction: &SearchDirection,
-    ) -> Option<(usize, usize)> {
+    ) -> Option<(usize, usize, usize)> {
+        // search raw not to break indices on multibyte chars
         match direction {
-            SearchDirection::Forward => {
-                for (row_i, row) in self.rows[row_idx..].iter().enumerate() {
-                    // search range is after cursor position
-                    if row_i == 0 {
-                        let idx = col_idx + 1;
-                        if idx > row.render.len() {
-                            continue;
-                        }
-                        if let Some(col_i) = row.render[idx..].find(query) {
-                            return Some((
-                                row_idx + row_i,
-                                row.convert_index_render_to_raw(idx + col_i),
-                            ));
-                        }
-                    } else {
-                        if let Some(col_i) = row.render.find(query) {
-                            return Some((row_idx + row_i, row.convert_index_render_to_raw(col_i)));
-                        }
-                    }
-                }
+            // search range is after cursor position
+            SearchDirection::Forward => self
+                .find_from(pattern, row_idx, col_idx, false)
+                .or_else(|| self.find_from(pattern, 0, 0, true)),
+            SearchDirection::Backward => self
+                .find_before(pattern, row_idx, col_idx)
+                .or_else(|| self.find_before(pattern, self.num_rows(), 0)),
+            SearchDirection::None => self.find_from(pattern, 0, 0, true),
+        }
+    }
+
+    // return the last match before (row_idx, col_idx)
+    fn find_before(
+        &self,
+        pattern: &Pattern,
+        row_idx: usize,
+        col_idx: usize,
+    ) -> Option<(usize, usize, usize)> {
+        let end = row_idx.min(self.num_rows().saturating_sub(1));
+        for row_i in (0..=end).rev() {
+            let line = self.line(row_i)?;
+            // search range is before cursor position
+            let found = if row_i == row_idx {
+                pattern
+                    .find_iter(&line)
+                    .filter(|(i, _)| *i < col_idx)
+                    .last()
+            } else {
+                pattern.find_iter(&line).last()
+            };
+            if let Some((start, end)) = found {
+                return Some((row_i, start, end));
             }
-            SearchDirection::Backward => {
-                for (row_i, row) in self.rows[..=row_idx].iter().enumerate().rev() {
-                    if row_i == row_idx {
-                        if col_idx > row.render.len() {
-                            continue;
-                        }
-
-                        if let Some(col_i) = row.render[..=col_idx].rfind(query) {
-                            return Some((row_i, row.convert_index_render_to_raw(col_i)));
-                        }
-                    } else {
-                        if let Some(col_i) = row.render.rfind(query) {
-                            return Some((row_i, row.convert_index_render_to_raw(col_i)));
-                        }
-                    }
-                }
+        }
+
+        None
+    }
+
+    // count matches from the start up to the limits, with the index of the match at
+    // (row_idx, col_idx). index is 0 if no match starts at the position or it's not counted.
+    pub fn count_matches(&self, pattern: &Pattern, row_idx: usize, col_idx: usize) -> MatchCount {
+        let mut count = MatchCount::default();
+        for row_i in 0..self.num_rows() {
+            if row_i == MAX_COUNTED_ROWS {
+                count.partial = true;
+                break;
             }
-            SearchDirection::None => {
-                for (row_i, row) in self.rows.iter().enumerate() {
-                    if let Some(col_i) = row.render.find(query) {
-                        return Some((row_i, row.convert_index_render_to_raw(col_i)));
-                    }
+            let line = self.line(row_i).unwrap_or_default();
+            for (start, _) in pattern.find_iter(&line) {
+                if count.total == MAX_COUNTED_MATCHES {
+                    count.partial = true;
+                    return count;
+                }
+                count.total += 1;
+                if row_i == row_idx && start == col_idx {
+                    count.index = count.total;
                 }
             }
         }
 
+        count
+    }
+
+    // return the first match at or after (row_idx, col_idx).
+    // a match starting at col_idx is included only if inclusive is true.
+    pub fn find_from(
+        &self,
+        pattern: &Pattern,
+        row_idx: usize,
+        col_idx: usize,
+        inclusive: bool,
+    ) -> Option<(usize, usize, usize)> {
+        for row_i in row_idx..self.num_rows() {
+            let line = self.line(row_i)?;
+            let found = if row_i == row_idx {
+                pattern
+                    .find_iter(&line)
+                    .find(|(i, _)| col_idx < *i || (inclusive && col_idx == *i))
+            } else {
+                pattern.find_iter(&line).next()
+            };
+            if let Some((start, end)) = found {
+                return Some((row_i, start, end));
+            }
+        }
+
         None
     }
 }
 
+fn is_bracket(c: char) -> bool {
+    BRACKETS
+        .iter()
+        .any(|&(open, close)| c == open || c == close)
+}
+
 impl Default for Content {
     fn default() -> Self {
-        Content {
+        Self {
             filename: None,
-            rows: Vec::<Row>::new(),
+            syntax: None,
+            line_ending: LineEnding::default(),
+            mixed_line_ending: false,
+            // new file ends with newline
+            final_newline: true,
+            encoding: Encoding::default(),
+            bom: false,
+            settings: FileSettings::default(),
+            saved_line_ending: LineEnding::default(),
+            tab_stop: Settings::default().tab_stop,
+            text: Rope::new(),
+            cache: BTreeMap::new(),
+            history: History::default(),
         }
     }
 }
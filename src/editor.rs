@@ -1,95 +1,224 @@
-use std::fs::{File, OpenOptions};
-use std::io::{stdin, stdout, Read, Stdout, Write};
-use std::{error, fmt, time};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::{mem, time};
 
-use termion::cursor::*;
 use termion::event;
-use termion::input::TermRead;
-use termion::raw::IntoRawMode;
-use termion::raw::RawTerminal;
-
-use crate::{content::*, row::*, QUIT_TIMES, VERSION};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+mod mouse;
+mod vim;
+
+use mouse::Click;
+use vim::Vim;
+
+use crate::{
+    content::*,
+    editorconfig,
+    encoding::{Encoding, LineEnding},
+    error::{self, Error},
+    file,
+    key::{self, Modifiers},
+    keymap::{self, Command, KeyPress, Keymap, Lookup},
+    motion::Scanner,
+    row::*,
+    screen::{Screen, Style},
+    search::{Pattern, SearchHighlight, SearchOptions},
+    settings::{FileSettings, Settings},
+    signal,
+    syntax::Highlight,
+    terminal::{Terminal, TermionTerminal},
+    window::{Layout, Separator, SplitDirection, Window},
+    VERSION,
+};
+
+// ratio of a split changed by resizing window once
+const WINDOW_RESIZE_STEP: f64 = 0.05;
 
 pub struct EditorConfig {
+    // text area of the current window
     pub cols: usize,
     pub rows: usize,
+    // area for windows, screen without status message bar
+    pub screen_cols: usize,
+    pub screen_rows: usize,
+    // keep `file~` on save
+    pub backup: bool,
+    // copy to system clipboard by OSC 52 escape sequence
+    pub osc52: bool,
+    pub settings: Settings,
+    // read settings files and the keymap file on run, off when given by the caller
+    pub read_settings: bool,
+    pub read_keymap: bool,
 }
 
 impl EditorConfig {
-    pub fn new(cols: usize, rows: usize) -> Self {
-        Self { cols, rows }
+    pub fn new(screen_cols: usize, screen_rows: usize) -> Self {
+        Self {
+            cols: screen_cols,
+            // row - 1 is for status bar
+            rows: screen_rows.saturating_sub(1),
+            screen_cols,
+            screen_rows,
+            backup: false,
+            osc52: false,
+            settings: Settings::default(),
+            read_settings: true,
+            read_keymap: true,
+        }
+    }
+
+    // load global and project settings files in order.
+    // return errors of each file.
+    pub fn load_settings(&mut self) -> Vec<String> {
+        Settings::paths()
+            .into_iter()
+            .filter_map(|path| {
+                self.settings
+                    .load_file(&path)
+                    .err()
+                    .map(|e| format!("{}: {}", path.display(), e))
+            })
+            .collect()
     }
 }
 
+// state of an opened file.
+// state of the displayed buffer is held by Editor,
+// this keeps state of the others.
+#[derive(Default)]
+struct Buffer {
+    content: Content,
+    cursor_x: usize,
+    cursor_y: usize,
+    row_offset: usize,
+    col_offset: usize,
+    dirty: bool,
+}
+
+// start of selection, selection is between mark and cursor
+#[derive(Debug, Clone, Copy)]
+struct Mark {
+    row: usize,
+    col: usize,
+    // set by shift+arrow, cleared by arrow without shift
+    shift: bool,
+}
+
+// text pasted just before, replaced by yank-pop
+#[derive(Debug, Clone, Copy)]
+struct Yank {
+    start: (usize, usize),
+    end: (usize, usize),
+    // index of kill_ring
+    index: usize,
+}
+
+// input from the terminal
+enum Input {
+    Key(event::Key),
+    Mouse(event::MouseEvent),
+}
+
+// max number of texts kept in kill ring
+const KILL_RING_CAPACITY: usize = 32;
+
 pub struct Editor {
     config: EditorConfig,
-    out: RawTerminal<Stdout>,
+    terminal: Box<dyn Terminal>,
+    // model of the terminal screen for differential rendering
+    screen: Screen,
     cursor_x: usize,
     cursor_y: usize,
     render_x: usize,
     content: Content,
     row_offset: usize,
     col_offset: usize,
+    // screen lines of the row at row_offset scrolled out in soft wrap mode
+    wrap_offset: usize,
     status_message: String,
     status_message_time: time::Instant,
     dirty: bool,
+    // matches of the current buffer painted during search
+    search: Option<SearchHighlight>,
+    // toggled in find and replace prompt, kept for the next search
+    search_options: SearchOptions,
+    // modifiers of the last key
+    modifiers: Modifiers,
+    mark: Option<Mark>,
+    // for double click
+    last_click: Option<Click>,
+    // left button is held after a click in the text
+    dragging: bool,
+    // the current window is scrolled by the mouse wheel, the cursor may be out of it
+    scrolled: bool,
+    // cut or copied texts, the last one is pasted
+    kill_ring: Vec<String>,
+    last_yank: Option<Yank>,
+    keymap: Keymap,
+    // prefix keys of a multi-key binding typed so far
+    pending_keys: Vec<KeyPress>,
+    // modal editing, None is modeless
+    vim: Option<Vim>,
+    // set by `:q`
+    quit: bool,
+    // all buffers. buffers[current_buffer] is a placeholder of the displayed buffer.
+    buffers: Vec<Buffer>,
+    current_buffer: usize,
+    // cursor and offsets of windows[current_window] are held by Editor
+    windows: Vec<Window>,
+    current_window: usize,
+    layout: Layout,
 }
 
-#[derive(Debug)]
-struct Error(String);
-
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
-impl error::Error for Error {}
-
-impl Error {
-    #[allow(dead_code)]
-    pub fn new<T>(msg: T) -> Self
-    where
-        T: Into<String>,
-    {
-        Self(msg.into())
+impl Editor {
+    // run on stdin and stdout in raw mode
+    pub fn new() -> error::Result<Self> {
+        let terminal = TermionTerminal::new()?;
+        Self::with_terminal(Box::new(terminal))
     }
-}
 
-impl Editor {
-    pub fn new() -> Self {
-        // change rawmode
-        // TODO: describe canonical mode and raw mode
-        // dropped stdout, restore original state
-        // into_raw_mode invoke libc::cfmakeraw()
-        // cfmakeraw() set like `version7` driver's row mode
-        //
-        // specifically, set flags below
-        // termios_p->c_iflag &= ~(IGNBRK | BRKINT | PARMRK | ISTRIP | INLCR | IGNCR | ICRNL | IXON)
-        // termios_p->c_oflag &= ~OPOST;
-        // termios_p->c_lflag &= ~(ECHO | ECHONL | ICANON | ISIG | IEXTEN);
-        // termios_p->c_cflag &= ~(CSIZE | PARENB);
-        // termios_p->c_cflag |= CS8;
-        //
-        // into_raw_mode: https://github.com/redox-os/termion/blob/dce5e7500fd709987f9bf8f3911e4daa61d0ad14/src/raw.rs#L101-L114
-        // raw_terminal_attr: https://github.com/redox-os/termion/blob/8054e082b01c3f45f89f0db96bc374f1e378deb1/src/sys/unix/attr.rs#L17-L19
-        let mut out = stdout().into_raw_mode().unwrap();
-        let (cols, rows) = Self::get_window_size(&mut out);
-        // row - 1 is for status bar, status messages
-        let config = EditorConfig::new(cols.into(), (rows - 2).into());
+    // run on the terminal, like a virtual terminal for tests
+    pub fn with_terminal(mut terminal: Box<dyn Terminal>) -> error::Result<Self> {
+        let (cols, rows) = terminal.size()?;
+        // row - 1 is for status messages
+        let config = EditorConfig::new(cols.into(), usize::from(rows).saturating_sub(1));
 
-        Self {
+        Ok(Self {
             config,
-            out,
+            terminal,
+            screen: Screen::new(cols.into(), rows.into()),
             cursor_x: 0,
             cursor_y: 0,
             render_x: 0,
             content: Content::default(),
             row_offset: 0,
             col_offset: 0,
+            wrap_offset: 0,
             status_message: String::new(),
             status_message_time: time::Instant::now(),
             dirty: false,
-        }
+            search: None,
+            search_options: SearchOptions::default(),
+            modifiers: Modifiers::default(),
+            mark: None,
+            last_click: None,
+            dragging: false,
+            scrolled: false,
+            kill_ring: Vec::new(),
+            last_yank: None,
+            keymap: Keymap::default(),
+            pending_keys: Vec::new(),
+            vim: None,
+            quit: false,
+            buffers: vec![Buffer::default()],
+            current_buffer: 0,
+            windows: vec![Window::default()],
+            current_window: 0,
+            layout: Layout::Window(0),
+        })
     }
 
     // In ASCII character code, control characters that are not displayed as characters
@@ -102,71 +231,140 @@ impl Editor {
         key & 0b001_1111
     }
 
-    pub fn run(&mut self, path: Option<String>) {
-        self.set_status_message("HELP: Ctrl-S = save | Ctrl-Q = quit | Ctrl-F = find");
-        match path {
-            Some(path) => self.open(path),
-            _ => {}
+    pub fn set_backup(&mut self, backup: bool) {
+        self.config.backup = backup;
+    }
+
+    pub fn set_osc52(&mut self, osc52: bool) {
+        self.config.osc52 = osc52;
+    }
+
+    // use the settings instead of settings files, like in tests
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.config.settings = settings;
+        self.config.read_settings = false;
+    }
+
+    // use the keymap instead of the keymap file
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+        self.config.read_keymap = false;
+    }
+
+    // edit until quit, fail when the input is closed
+    pub fn run(&mut self, paths: Vec<String>) -> error::Result<()> {
+        self.set_status_message(
+            "HELP: Ctrl-S = save | Ctrl-Q = quit | Ctrl-F = find | Ctrl-R = replace | Ctrl-Space = mark | Ctrl-X/Ctrl-C/Ctrl-V = cut/copy/paste | Ctrl-Z = undo | Ctrl-Y = redo | Ctrl-E = LF/CRLF | Ctrl-O = open | Ctrl-N/Ctrl-P = next/prev buffer | Ctrl-B = buffers | Alt-S/Alt-V = split | Alt-O = other window | Alt-C = close window",
+        );
+        let mut errors = if self.config.read_settings {
+            self.config.load_settings()
+        } else {
+            Vec::new()
         };
+        if let Err(e) = signal::watch_resize() {
+            errors.push(format!("SIGWINCH: {}", e));
+        }
+        self.apply_settings();
+        let keymap_path = file::config_dir()
+            .map(|dir| dir.join("keymap"))
+            .filter(|_| self.config.read_keymap);
+        if let Some(path) = keymap_path {
+            if let Err(e) = self.keymap.load_file(&path) {
+                errors.push(format!("{}: {}", path.display(), e));
+            }
+        }
+        for path in paths {
+            if let Err(e) = self.open(path) {
+                errors.push(e.to_string());
+            }
+        }
+        self.switch_buffer(0);
+        if !errors.is_empty() {
+            self.set_status_message(errors.join(" | "));
+        }
 
-        let mut quit_times = QUIT_TIMES;
+        // number of times quit is pressed
+        let mut quit_presses: u8 = 0;
 
         loop {
             // to render error message
-            let mut res: Result<(), Box<dyn error::Error>> = Ok(());
+            let mut res: error::Result<()> = Ok(());
 
             self.refresh_screen();
-            match self.read_key() {
-                // if k == ctrl_key(b'q')
-                event::Key::Ctrl('q') => {
-                    quit_times -= 1;
-
-                    if self.dirty && 0 < quit_times {
-                        let msg = format!("warning. file has unsaved changes. press Ctrl-Q {} more times to quit.", quit_times);
-                        self.set_status_message(msg);
-
-                        continue;
-                    }
-                    self.reset_screen_on_end();
-                    break;
-                }
-                event::Key::Ctrl('s') => {
-                    res = self.save();
+            let key = match self.read_input() {
+                Ok(Input::Key(key)) => key,
+                Ok(Input::Mouse(event)) => {
+                    self.mouse(event);
+                    continue;
                 }
-                event::Key::Ctrl('f') => {
-                    // res = self.find();
-                    self.find();
-                }
-                k @ (event::Key::Up
-                | event::Key::Left
-                | event::Key::Down
-                | event::Key::Right
-                | event::Key::PageUp
-                | event::Key::PageDown
-                | event::Key::Home
-                | event::Key::End) => self.update_cursor_state(&k),
-                event::Key::Backspace | event::Key::Ctrl('h') => {
-                    res = self.backspace_char();
+                Err(e) => {
+                    self.reset_screen_on_end();
+                    return Err(e);
                 }
-                event::Key::Delete => {
-                    self.update_cursor_state(&event::Key::Right);
-                    res = self.backspace_char();
+            };
+            self.scrolled = false;
+            // yank-pop is available just after paste
+            let last_yank = self.last_yank.take();
+            match self.vim_key(&key) {
+                Ok(false) => {}
+                Ok(true) => {
+                    if self.quit {
+                        self.reset_screen_on_end();
+                        break Ok(());
+                    }
+                    continue;
                 }
-                // Enter
-                event::Key::Char('\n') | event::Key::Char('\r') => {
-                    res = self.insert_new_line();
+                Err(e) => {
+                    self.set_status_message(format!("{}", e));
+                    continue;
                 }
-                event::Key::Ctrl('l') | event::Key::Char('\x1b') => {
-                    // TODO
+            }
+            self.pending_keys.push(KeyPress::new(key, self.modifiers));
+            let command = match self.keymap.lookup(&self.pending_keys) {
+                Lookup::Pending => {
+                    let msg = format!("{} -", keymap::keys_name(&self.pending_keys));
+                    self.set_status_message(msg);
+                    continue;
                 }
-                event::Key::Char(c) => {
-                    res = self.insert_char(c);
+                Lookup::Command(command) => Some(command),
+                Lookup::Unbound => None,
+            };
+            let keys = mem::take(&mut self.pending_keys);
+
+            match command {
+                Some(Command::Quit) => {
+                    quit_presses = quit_presses.saturating_add(1);
+                    // quit_times can be changed by :set while warning
+                    let quit_times = self.config.settings.quit_times.saturating_sub(quit_presses);
+
+                    let dirty_buffers = self.dirty_buffers();
+                    if !dirty_buffers.is_empty() && 0 < quit_times {
+                        let msg = format!(
+                            "warning. {} has unsaved changes. press {} {} more times to quit.",
+                            dirty_buffers.join(", "),
+                            keymap::keys_name(&keys),
+                            quit_times
+                        );
+                        self.set_status_message(msg);
+
+                        continue;
+                    }
+                    self.reset_screen_on_end();
+                    break Ok(());
                 }
-                x => {
-                    // nop
-                    let msg = format!("key: {:?}", x);
-                    self.set_status_message(msg);
+                Some(command) => {
+                    res = self.execute(command, last_yank);
                 }
+                // unbound chars are typed in, except control chars
+                None => match key {
+                    event::Key::Char(c) if keys.len() == 1 && (c == '\t' || !c.is_control()) => {
+                        res = self.insert_char(c);
+                    }
+                    _ => {
+                        let msg = format!("{} is undefined", keymap::keys_name(&keys));
+                        self.set_status_message(msg);
+                    }
+                },
             }
 
             if let Err(e) = res {
@@ -175,92 +373,543 @@ impl Editor {
         }
     }
 
-    // TODO: fix error handling
-    fn read_key(&self) -> event::Key {
+    // run a command bound in the keymap
+    fn execute(&mut self, command: Command, last_yank: Option<Yank>) -> error::Result<()> {
+        match command {
+            // handled by the main loop to count quit times
+            Command::Quit => {}
+            Command::Save => {
+                return self.save();
+            }
+            Command::Find => {
+                self.content.break_undo_group();
+                self.find();
+            }
+            Command::Replace => {
+                self.content.break_undo_group();
+                return self.replace();
+            }
+            Command::ToggleLineEnding => {
+                self.toggle_line_ending();
+            }
+            Command::Open => {
+                return self.open_prompt();
+            }
+            Command::Set => {
+                return self.set_prompt();
+            }
+            Command::NextBuffer => {
+                let n = (self.current_buffer + 1) % self.buffers.len();
+                self.select_buffer(n);
+            }
+            Command::PreviousBuffer => {
+                let len = self.buffers.len();
+                self.select_buffer((self.current_buffer + len - 1) % len);
+            }
+            Command::PickBuffer => {
+                self.pick_buffer();
+            }
+            Command::SplitHorizontal => {
+                self.split_window(SplitDirection::Horizontal);
+            }
+            Command::SplitVertical => {
+                self.split_window(SplitDirection::Vertical);
+            }
+            Command::CloseWindow => {
+                self.close_window();
+            }
+            Command::NextWindow => {
+                self.focus_window((self.current_window + 1) % self.windows.len());
+            }
+            Command::GrowWindow => {
+                self.resize_window(WINDOW_RESIZE_STEP);
+            }
+            Command::ShrinkWindow => {
+                self.resize_window(-WINDOW_RESIZE_STEP);
+            }
+            Command::Undo => {
+                return self.undo();
+            }
+            Command::Redo => {
+                return self.redo();
+            }
+            Command::SetMark => {
+                self.toggle_mark();
+            }
+            Command::Cut => {
+                return self.cut();
+            }
+            Command::Copy => {
+                self.copy();
+            }
+            Command::Paste => {
+                return self.paste();
+            }
+            Command::YankPop => {
+                return self.yank_pop(last_yank);
+            }
+            Command::MoveUp
+            | Command::MoveDown
+            | Command::MoveLeft
+            | Command::MoveRight
+            | Command::PageUp
+            | Command::PageDown
+            | Command::LineStart
+            | Command::LineEnd => {
+                let key = match command {
+                    Command::MoveUp => event::Key::Up,
+                    Command::MoveDown => event::Key::Down,
+                    Command::MoveLeft => event::Key::Left,
+                    Command::MoveRight => event::Key::Right,
+                    Command::PageUp => event::Key::PageUp,
+                    Command::PageDown => event::Key::PageDown,
+                    Command::LineStart => event::Key::Home,
+                    _ => event::Key::End,
+                };
+                // moving cursor finishes current undo step
+                self.content.break_undo_group();
+                self.update_mark(self.modifiers.shift);
+                self.update_cursor_state(&key);
+            }
+            Command::MoveWordLeft
+            | Command::MoveWordRight
+            | Command::MoveParagraphUp
+            | Command::MoveParagraphDown
+            | Command::FileStart
+            | Command::FileEnd => {
+                self.content.break_undo_group();
+                self.update_mark(self.modifiers.shift);
+                let last_row = self.num_rows().saturating_sub(1);
+                let mut scanner = Scanner::new(&self.content, self.word_chars());
+                let pos = (self.cursor_y, self.cursor_x);
+                let (row, col) = match command {
+                    Command::MoveWordLeft => scanner.word_backward(pos),
+                    Command::MoveWordRight => scanner.word_forward(pos),
+                    Command::MoveParagraphUp => scanner.paragraph_backward(pos),
+                    Command::MoveParagraphDown => scanner.paragraph_forward(pos),
+                    Command::FileStart => (0, 0),
+                    _ => scanner.line_end(last_row),
+                };
+                self.cursor_y = row;
+                self.cursor_x = col;
+            }
+            Command::DeleteBackward => {
+                return self.backspace_char();
+            }
+            Command::DeleteForward => {
+                if self.selection().is_some() {
+                    return self.delete_selection();
+                }
+                return self.delete_forward_char();
+            }
+            Command::DeleteWordBackward => {
+                return self.delete_word(false);
+            }
+            Command::DeleteWordForward => {
+                return self.delete_word(true);
+            }
+            Command::Newline => {
+                return self.insert_new_line();
+            }
+            Command::Indent => {
+                return self.indent_lines(true);
+            }
+            Command::Outdent => {
+                return self.indent_lines(false);
+            }
+            Command::MatchBracket => match self.matching_bracket() {
+                Some(pos) => {
+                    self.content.break_undo_group();
+                    self.update_mark(self.modifiers.shift);
+                    (self.cursor_y, self.cursor_x) = pos;
+                }
+                None => self.set_status_message("no matching bracket"),
+            },
+        }
+
+        Ok(())
+    }
+
+    // key ignoring mouse events, fail when the input is closed
+    fn read_key(&mut self) -> error::Result<event::Key> {
+        loop {
+            if let Input::Key(key) = self.read_input()? {
+                return Ok(key);
+            }
+        }
+    }
+
+    fn read_input(&mut self) -> error::Result<Input> {
+        if self.terminal.take_resized() {
+            self.resize();
+        }
         // waiting input
-        if let Some(k) = stdin().keys().next() {
-            return k.unwrap();
-        } else {
-            panic!("failed getting key input")
+        loop {
+            match self.terminal.read_event() {
+                Ok(event::Event::Key(k)) => {
+                    self.modifiers = Modifiers::default();
+                    return Ok(Input::Key(k));
+                }
+                // modified special keys are not parsed by termion
+                Ok(event::Event::Unsupported(seq)) => {
+                    if let Some((k, modifiers)) = key::parse_modified_key(&seq) {
+                        self.modifiers = modifiers;
+                        return Ok(Input::Key(k));
+                    }
+                }
+                Ok(event::Event::Mouse(e)) => return Ok(Input::Mouse(e)),
+                // reading is interrupted by SIGWINCH
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                    if self.terminal.take_resized() {
+                        self.resize();
+                    }
+                }
+                Err(e) => return Err(Error::Io(e)),
+            }
         }
     }
 
-    fn open(&mut self, path: String) {
-        let mut f = File::open(&path).unwrap();
-        // read_line returns string when \r or \n appear
-        // read only one row
-        let mut content_string = String::with_capacity(4096);
+    // open the file as a new buffer and display it.
+    // if the file is already opened, display the buffer.
+    fn open(&mut self, path: String) -> error::Result<()> {
+        if let Some(i) = (0..self.buffers.len()).find(|i| self.buffer_name(*i) == path) {
+            self.switch_buffer(i);
+            return Ok(());
+        }
 
-        let _ = f.read_to_string(&mut content_string);
-        let content = Content::from_text(path, &content_string);
+        // missing file is opened as a new file, and created on save
+        let mut bytes = Vec::with_capacity(4096);
+        let new_file = match File::open(&path).and_then(|mut f| f.read_to_end(&mut bytes)) {
+            Ok(_) => false,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => true,
+            // like permission denied or a directory
+            Err(e) => {
+                let msg = format!("{}: {}", path, e);
+                return Err(Error::Io(io::Error::new(e.kind(), msg)));
+            }
+        };
+        let content = if new_file {
+            Content::new_file(path.clone())
+        } else {
+            Content::from_bytes(path.clone(), &bytes)
+        };
+        // notes of the opened file, like it is saved differently from how it was read
+        let mut warnings = Vec::new();
+        if new_file {
+            warnings.push("new file".to_string());
+        }
+        if content.encoding == Encoding::Latin1 {
+            warnings.push("not valid UTF-8, opened as latin-1".to_string());
+        }
+        if content.mixed_line_ending {
+            let name = content.line_ending.name();
+            warnings.push(format!("mixed line endings, saved as {}", name));
+        }
 
+        // replace the empty buffer opened at start up
+        if !self.content.is_phantom() || 0 < self.num_rows() || self.dirty {
+            self.buffers.push(Buffer::default());
+            self.switch_buffer(self.buffers.len() - 1);
+        }
         self.content = content;
+        self.resolve_file_settings();
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.row_offset = 0;
+        self.col_offset = 0;
+        self.wrap_offset = 0;
         self.dirty = false;
+        if !warnings.is_empty() {
+            self.set_status_message(format!("{}: {}", path, warnings.join(", ")));
+        }
+
+        Ok(())
+    }
+
+    fn open_prompt(&mut self) -> error::Result<()> {
+        match self.prompt("open: ", None) {
+            Some(path) => {
+                self.open(path)?;
+                self.select_buffer(self.current_buffer);
+                Ok(())
+            }
+            None => {
+                self.set_status_message("open aborted");
+                Ok(())
+            }
+        }
+    }
+
+    // display buffers[n], state of the current buffer is kept in buffers
+    fn switch_buffer(&mut self, n: usize) {
+        if self.buffers.len() <= n || n == self.current_buffer {
+            return;
+        }
+
+        let current = &mut self.buffers[self.current_buffer];
+        current.cursor_x = self.cursor_x;
+        current.cursor_y = self.cursor_y;
+        current.row_offset = self.row_offset;
+        current.col_offset = self.col_offset;
+
+        self.swap_buffer(n);
+        let next = &self.buffers[n];
+        self.cursor_x = next.cursor_x;
+        self.cursor_y = next.cursor_y;
+        self.row_offset = next.row_offset;
+        self.col_offset = next.col_offset;
+        self.wrap_offset = 0;
+        self.clamp_cursor();
+    }
+
+    // display content of buffers[n] without changing cursor
+    fn swap_buffer(&mut self, n: usize) {
+        if n == self.current_buffer {
+            return;
+        }
+
+        self.exit_insert_mode();
+        self.content.break_undo_group();
+        let current = &mut self.buffers[self.current_buffer];
+        current.content = mem::take(&mut self.content);
+        current.dirty = self.dirty;
+
+        let next = &mut self.buffers[n];
+        self.content = mem::take(&mut next.content);
+        self.dirty = next.dirty;
+        self.search = None;
+        self.mark = None;
+        self.current_buffer = n;
+    }
+
+    // the buffer may be edited in the other window, keep cursor in the content
+    fn clamp_cursor(&mut self) {
+        self.cursor_y = self.cursor_y.min(self.num_rows());
+        let cursor_x = self.cursor_x;
+        self.cursor_x = self.current_row().map_or(0, |row| {
+            row.convert_index_render_to_raw(row.convert_index_raw_to_render(cursor_x))
+        });
+    }
+
+    // save cursor and offsets of the current window
+    fn stash_window(&mut self) {
+        let window = &mut self.windows[self.current_window];
+        window.buffer = self.current_buffer;
+        window.cursor_x = self.cursor_x;
+        window.cursor_y = self.cursor_y;
+        window.render_x = self.render_x;
+        window.row_offset = self.row_offset;
+        window.col_offset = self.col_offset;
+        window.wrap_offset = self.wrap_offset;
+    }
+
+    fn load_window(&mut self, n: usize) {
+        let window = self.windows[n].clone();
+        self.current_window = n;
+        self.mark = None;
+        self.scrolled = false;
+        self.swap_buffer(window.buffer);
+        self.cursor_x = window.cursor_x;
+        self.cursor_y = window.cursor_y;
+        self.row_offset = window.row_offset;
+        self.col_offset = window.col_offset;
+        self.wrap_offset = window.wrap_offset;
+        self.config.rows = window.rows;
+        self.config.cols = window.text_cols();
+        self.clamp_cursor();
+    }
+
+    fn focus_window(&mut self, n: usize) {
+        if self.windows.len() <= n || n == self.current_window {
+            return;
+        }
+        self.stash_window();
+        self.load_window(n);
+    }
+
+    fn split_window(&mut self, direction: SplitDirection) {
+        self.stash_window();
+        let new_window = self.windows.len();
+        self.windows.push(self.windows[self.current_window].clone());
+        self.layout
+            .split(self.current_window, new_window, direction);
+        self.arrange_windows();
+        self.focus_window(new_window);
+    }
+
+    fn close_window(&mut self) {
+        if self.windows.len() == 1 {
+            self.set_status_message("can't close the last window");
+            return;
+        }
+
+        let closed = self.current_window;
+        self.windows.remove(closed);
+        let layout = mem::replace(&mut self.layout, Layout::Window(0));
+        // at least one window is left
+        self.layout = layout.remove(closed).unwrap_or(Layout::Window(0));
+        // the closed window may be the last one, current_window is out of range
+        self.load_window(closed.min(self.windows.len() - 1));
+        self.arrange_windows();
+    }
+
+    fn resize_window(&mut self, delta: f64) {
+        if !self.layout.resize(self.current_window, delta) {
+            self.set_status_message("window is not split");
+        }
+        self.arrange_windows();
+    }
+
+    // return separators between windows
+    fn arrange_windows(&mut self) -> Vec<Separator> {
+        let separators = self.layout.arrange(
+            0,
+            0,
+            self.config.screen_rows,
+            self.config.screen_cols,
+            &mut self.windows,
+        );
+        for i in 0..self.windows.len() {
+            let window = &self.windows[i];
+            let num_rows = if i == self.current_window || window.buffer == self.current_buffer {
+                self.content.num_rows()
+            } else {
+                self.buffers[window.buffer].content.num_rows()
+            };
+            self.windows[i].gutter = self.config.settings.gutter_width(num_rows, window.cols);
+        }
+        let window = &self.windows[self.current_window];
+        self.config.rows = window.rows;
+        self.config.cols = window.text_cols();
+
+        separators
+    }
+
+    // switch buffer and show it in status message
+    fn select_buffer(&mut self, n: usize) {
+        self.switch_buffer(n);
+        let msg = format!(
+            "buffer {}/{}: {}",
+            self.current_buffer + 1,
+            self.buffers.len(),
+            self.buffer_name(self.current_buffer)
+        );
+        self.set_status_message(msg);
+    }
+
+    fn pick_buffer(&mut self) {
+        let list = (0..self.buffers.len())
+            .map(|i| {
+                let mark = if self.is_buffer_dirty(i) { "+" } else { "" };
+                format!("{}:{}{}", i + 1, self.buffer_name(i), mark)
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        let prompt = format!("buffers: {} | switch to: ", list);
+
+        if let Some(input) = self.prompt(&prompt, None) {
+            match input.trim().parse::<usize>() {
+                Ok(n) if 0 < n && n <= self.buffers.len() => self.select_buffer(n - 1),
+                _ => self.set_status_message(format!("no buffer: {}", input)),
+            }
+        }
+    }
+
+    fn buffer_name(&self, n: usize) -> String {
+        let filename = if n == self.current_buffer {
+            &self.content.filename
+        } else {
+            &self.buffers[n].content.filename
+        };
+
+        filename.clone().unwrap_or_else(|| "[No Name]".to_string())
+    }
+
+    fn is_buffer_dirty(&self, n: usize) -> bool {
+        if n == self.current_buffer {
+            self.dirty
+        } else {
+            self.buffers[n].dirty
+        }
+    }
+
+    // return names of buffers which have unsaved changes
+    fn dirty_buffers(&self) -> Vec<String> {
+        (0..self.buffers.len())
+            .filter(|i| self.is_buffer_dirty(*i))
+            .map(|i| self.buffer_name(i))
+            .collect()
     }
 
     fn update_cursor_state(&mut self, key: &event::Key) {
         match key {
+            event::Key::Up | event::Key::Down if self.config.settings.soft_wrap => {
+                self.move_wrapped_line(*key == event::Key::Up);
+            }
             event::Key::Up => {
                 if 0 < self.cursor_y {
+                    let render_x = self.current_render_x();
                     self.cursor_y -= 1;
-                    self.cursor_x = std::cmp::min(
-                        self.current_row().map_or(0, |row| row.raw.len()),
-                        self.cursor_x,
-                    );
+                    self.cursor_x = self
+                        .current_row()
+                        .map_or(0, |row| row.convert_index_render_to_raw(render_x));
                 }
             }
             // left Left Arrow is \x1b[D
             event::Key::Left => {
                 if 0 < self.cursor_x {
-                    self.cursor_x -= 1;
+                    let cursor_x = self.cursor_x;
+                    self.cursor_x = self
+                        .current_row()
+                        .map_or(0, |row| row.prev_boundary(cursor_x));
                 } else if 0 < self.cursor_y {
                     self.cursor_y -= 1;
-                    match self.current_row() {
-                        Some(current_row) => {
-                            self.cursor_x = if 0 < current_row.raw.len() {
-                                current_row.raw.len()
-                            } else {
-                                0
-                            };
-                        }
-                        None => {}
+                    if let Some(current_row) = self.current_row() {
+                        self.cursor_x = current_row.raw.len();
                     }
                 }
             }
             // down Down Arrow is \x1b[B
             event::Key::Down => {
                 if self.cursor_y < self.num_rows() {
+                    let render_x = self.current_render_x();
                     self.cursor_y += 1;
-                    self.cursor_x = std::cmp::min(
-                        self.current_row().map_or(0, |row| row.raw.len()),
-                        self.cursor_x,
-                    );
+                    self.cursor_x = self
+                        .current_row()
+                        .map_or(0, |row| row.convert_index_render_to_raw(render_x));
                 }
             }
             // right Right Arrow is \x1b[C
             event::Key::Right => {
+                let cursor_x = self.cursor_x;
                 if let Some(current_row) = self.current_row() {
                     let len = current_row.raw.len();
-                    if 0 < len && self.cursor_x < len {
-                        self.cursor_x += 1;
+                    if 0 < len && cursor_x < len {
+                        self.cursor_x = current_row.next_boundary(cursor_x);
                     } else {
                         self.cursor_y += 1;
                         self.cursor_x = 0;
                     }
                 }
             }
-            // pageup is \x1b[5~
-            event::Key::PageUp => {
-                self.cursor_y = self.row_offset;
-            }
-            // pagedown is \x1b[6~
-            event::Key::PageDown => {
-                if self.cursor_y < self.config.rows {
-                    self.cursor_y = self.row_offset + self.config.rows - 1;
-                    if self.num_rows() < self.cursor_y {
-                        self.cursor_y = self.num_rows();
-                    }
+            // pageup is \x1b[5~, pagedown is \x1b[6~.
+            // scroll a window of rows, the cursor keeps its place in the window.
+            event::Key::PageUp | event::Key::PageDown => {
+                let rows = self.config.rows.max(1);
+                let render_x = self.current_render_x();
+                if *key == event::Key::PageUp {
+                    self.cursor_y = self.cursor_y.saturating_sub(rows);
+                    self.row_offset = self.row_offset.saturating_sub(rows);
+                } else {
+                    // the last row stays at the bottom of the window
+                    let max_offset = self.num_rows().saturating_sub(rows).max(self.row_offset);
+                    self.cursor_y = (self.cursor_y + rows).min(self.num_rows());
+                    self.row_offset = (self.row_offset + rows).min(max_offset);
                 }
+                self.wrap_offset = 0;
+                self.cursor_x = self
+                    .current_row()
+                    .map_or(0, |row| row.convert_index_render_to_raw(render_x));
             }
             // home depends on OS.
             // colud be \x1b[1~, \x1b[7~, \x1b[H, \x1b[0H
@@ -284,207 +933,418 @@ impl Editor {
 
         // If there is a short line next to a long line, the cursor position can be moved to a place without characters.
         // if cursor_x > row_len, cursor_x = row_len.
-        match self.current_row() {
-            Some(row) => {
-                if self.cursor_x > row.raw.len() {
-                    self.cursor_x = row.raw.len();
-                }
-            }
-            None => {}
-        }
-    }
-
-    // return (col, row)
-    fn get_window_size(out: &mut Stdout) -> (u16, u16) {
-        match termion::terminal_size() {
-            Ok(ts) => ts,
-            Err(_) => {
-                // get termsize manually
-                // move cursor bottom right
-                // \x1b[nC (n: natural number) move cursor to right direction amount of n
-                // \x1b[nB (n: natural number) move cursor to bottom direction amount of n
-                // print!("\x1b[999C\x1b[999B");
-                print!("{}", termion::cursor::Goto(999, 999));
-                out.flush().unwrap();
-
-                // then, get cursor position
-                // get_cursor_pos()
-                out.cursor_pos().unwrap()
+        if let Some(len) = self.current_row().map(|row| row.raw.len()) {
+            if self.cursor_x > len {
+                self.cursor_x = len;
             }
         }
     }
 
-    // return (col, row)
-    #[allow(dead_code)]
-    fn get_cursor_pos(out: &mut Stdout) -> (u16, u16) {
-        // get terminal status by \x1b[6n (https://vt100.net/docs/vt100-ug/chapter3.html#DSR)
-        // temrinal responses to stdin (http://vt100.net/docs/vt100-ug/chapter3.html#CPR)
-        // like `\x1b[{row};{col}R`
-        // after print \x1b[6n, parse response
-        print!("\x1b[6n");
-        print!("\r\n");
-        out.flush().unwrap();
-
-        let mut response = Vec::<u8>::new();
-
-        // parse terminal response
-        // get cols and rows from `\x1b[{cols};{rows}`
-        for b in stdin().bytes() {
-            match b.unwrap() {
-                b'\x1b' | b'[' => {}
-                b'R' => break,
-                b => response.push(b),
-            }
-        }
-        let row_col = String::from_utf8(response).unwrap();
-        let row_col = row_col.splitn(2, ';').collect::<Vec<&str>>();
-        let row_col: Vec<u16> = row_col
-            .iter()
-            .map(|num_str| num_str.parse::<u16>().unwrap())
-            .collect();
-
-        (row_col[1], row_col[0])
+    // recompute the screen size after the terminal is resized and redraw.
+    // sizes of windows and offsets are updated by refresh_screen.
+    fn resize(&mut self) {
+        // keep the old size if unknown
+        let Ok((cols, rows)) = self.terminal.size() else {
+            return;
+        };
+        self.config.screen_cols = cols.into();
+        // row - 1 is for status messages
+        self.config.screen_rows = usize::from(rows).saturating_sub(1);
+        // the old screen may be wrapped by the terminal, redraw everything
+        self.screen.resize(cols.into(), rows.into());
+        self.refresh_screen();
     }
 
     fn reset_screen_on_end(&mut self) {
-        print!("\x1b[2j");
-        print!("\x1b[H");
-        self.out.flush().unwrap();
+        self.screen.invalidate();
+        let _ = self.terminal.write("\x1b[2j\x1b[H");
     }
 
     fn refresh_screen(&mut self) {
+        let separators = self.arrange_windows();
         self.editor_scroll();
-        // \x1b is escape character
-        // this is write escape sequence to terminal
-        // example: \x1b[2j
-        // \x1b = ESC
-        // [    = ANSI control sequence indtoducer
-        // 2j   = erase entire screen
-
-        // set mode; change to ANSI/VT52 mode
-        print!("\x1b[?25l");
-        // moves cursor to home position (1, 1)
-        print!("\x1b[H");
-
-        self.draw_rows();
-        self.draw_status_bar();
+        self.stash_window();
+        // a frame is drawn into the screen model,
+        // and only changed cells are written to the terminal
+        self.screen.clear();
+
+        for i in 0..self.windows.len() {
+            self.draw_window(i);
+        }
+        for separator in separators.iter() {
+            for y in separator.top..separator.top + separator.height {
+                self.screen.put(separator.left, y, "|", Style::default());
+            }
+        }
         self.draw_status_message_bar();
 
         // set cursor position current state of cursor
-        // \x1b[{line};{column}
         // cursor_y range is less than numrows
         // therefore, it may exceed the rows of the window
         // to solve this problem, draw the value (cursor_y - row_offset)
-        print!(
-            "\x1b[{};{}H",
-            (self.cursor_y - self.row_offset) + 1,
-            (self.render_x - self.col_offset) + 1
-        );
+        let position = if self.cursor_y < self.row_offset || self.render_x < self.col_offset {
+            // scrolled away by the mouse wheel
+            None
+        } else if self.config.settings.soft_wrap {
+            Some(self.wrap_cursor_position())
+        } else {
+            Some((
+                self.cursor_y - self.row_offset,
+                self.render_x - self.col_offset,
+            ))
+        };
+        let window = &self.windows[self.current_window];
+        match position {
+            Some((y, x)) if !self.scrolled || y < window.rows => {
+                self.screen
+                    .set_cursor(window.left + window.gutter + x, window.top + y);
+            }
+            _ => self.screen.hide_cursor(),
+        }
+
+        // nothing is written when the frame is the same as the last one
+        if let Some(out) = self.screen.render() {
+            // nothing can be shown if the terminal is broken,
+            // reading the next key fails then
+            let _ = self.terminal.write(&out);
+        }
+    }
+
+    fn draw_window(&mut self, n: usize) {
+        let window = self.windows[n].clone();
+        let dirty = self.is_buffer_dirty(window.buffer);
+        let selection = self.selection().filter(|_| n == self.current_window);
+        let bracket = if n == self.current_window && self.config.settings.match_brackets {
+            self.matching_bracket()
+        } else {
+            None
+        };
+        let (content, search) = if window.buffer == self.current_buffer {
+            (&mut self.content, self.search.as_ref())
+        } else {
+            (&mut self.buffers[window.buffer].content, None)
+        };
 
-        // reset mode (change to screen mode)
-        print!("\x1b[?25h");
-        self.out.flush().unwrap();
+        let screen = &mut self.screen;
+        Self::draw_rows(
+            screen,
+            content,
+            &window,
+            search,
+            selection,
+            bracket,
+            &self.config.settings,
+        );
+        let search = search.filter(|_| n == self.current_window);
+        Self::draw_status_bar(
+            screen,
+            content,
+            &window,
+            dirty,
+            n == self.current_window,
+            search,
+        );
     }
 
-    fn draw_rows(&mut self) {
+    fn draw_rows(
+        screen: &mut Screen,
+        content: &mut Content,
+        window: &Window,
+        search: Option<&SearchHighlight>,
+        selection: Option<((usize, usize), (usize, usize))>,
+        bracket: Option<(usize, usize)>,
+        settings: &Settings,
+    ) {
         // draw `~` terminal rows number
-        let rows = self.config.rows;
-        let cols = self.config.cols;
+        let rows = window.rows;
+        let cols = window.text_cols();
+        let num_rows = content.num_rows();
+        // left of the text area
+        let left = window.left + window.gutter;
+        // (row idx, screen line in the row, start and end render index) of each screen line
+        let lines: Vec<(usize, usize, usize, usize)> = if settings.soft_wrap {
+            wrap_lines(content, window.row_offset, window.wrap_offset, rows, cols)
+        } else {
+            (0..rows)
+                .map(|i| {
+                    let start = window.col_offset;
+                    (i + window.row_offset, 0, start, start + cols)
+                })
+                .collect()
+        };
         (0..rows).for_each(|i| {
-            let filerow = i + self.row_offset;
-            if filerow < self.num_rows() {
-                let range = if self.content.rows[filerow].render.len() < self.col_offset {
-                    // no content in display range
-                    0..0
+            let y = window.top + i;
+            let (filerow, line, col_offset, col_end) = lines[i];
+            if 0 < window.gutter {
+                // continued lines of a wrapped row have no number
+                let number = if filerow < num_rows && line == 0 {
+                    let number = if settings.relative_numbers && filerow != window.cursor_y {
+                        filerow.abs_diff(window.cursor_y)
+                    } else {
+                        filerow + 1
+                    };
+                    number.to_string()
                 } else {
-                    let end = self.col_offset
-                        + (self.content.rows[filerow].render.len() - self.col_offset).min(cols);
-                    self.col_offset..end
+                    String::new()
+                };
+                let number = format!("{:>width$} ", number, width = window.gutter - 1);
+                let style = Style::fg(settings.color(Highlight::LineNumber));
+                screen.put(window.left, y, &number, style);
+            }
+            if let Some(row) = content.row_from_index(filerow) {
+                let mut hl = row.hl.clone();
+                if let Some(search) = search {
+                    search.highlight_row(filerow, row, &mut hl);
+                }
+                if let Some((_, col)) = bracket.filter(|(row, _)| *row == filerow) {
+                    if let Some(kind) = hl.get_mut(row.convert_index_raw_to_hl(col)) {
+                        *kind = Highlight::Bracket;
+                    }
+                }
+                // hl index range of selection in this row
+                let selected = match selection {
+                    Some((start, end)) if start.0 <= filerow && filerow <= end.0 => {
+                        let start = if start.0 == filerow {
+                            row.convert_index_raw_to_hl(start.1)
+                        } else {
+                            0
+                        };
+                        let end = if end.0 == filerow {
+                            row.convert_index_raw_to_hl(end.1)
+                        } else {
+                            hl.len()
+                        };
+                        start..end
+                    }
+                    _ => 0..0,
                 };
+                // render index (column) and hl index (char index) of the grapheme
+                let mut render_x = 0;
+                let mut hl_x = 0;
+                for g in row.render.graphemes(true) {
+                    let width = g.width();
+                    let kind = hl.get(hl_x).copied().unwrap_or(Highlight::Normal);
+                    let start = render_x;
+                    render_x += width;
+                    hl_x += g.chars().count();
+
+                    if render_x <= col_offset || (width == 0 && start < col_offset) {
+                        continue;
+                    }
+                    if col_end < render_x {
+                        // wide char at the right edge is not displayable
+                        break;
+                    }
 
-                print!("{}", &self.content.rows[filerow].render[range]);
-            } else {
-                if i == rows / 3 && self.num_rows() == 0 {
-                    let msg = format!("kiroro editor -- version {}", VERSION);
-                    let msg_len = msg.len().min(cols as usize);
-                    let padding_space_count = (cols as usize - msg_len) / 2;
-                    print!(
-                        "~{}{}",
-                        " ".repeat(padding_space_count - 1),
-                        &msg[..msg_len]
-                    );
-                } else {
-                    print!("~");
+                    // current match and selection are drawn in reverse video
+                    let style = Style {
+                        fg: settings.color(kind),
+                        bold: false,
+                        reverse: kind == Highlight::CurrentMatch
+                            || kind == Highlight::Bracket
+                            || selected.contains(&(hl_x - g.chars().count())),
+                    };
+                    if start < col_offset {
+                        // wide char cut by the left edge
+                        screen.put(left, y, &" ".repeat(render_x - col_offset), style);
+                    } else {
+                        screen.put(left + start - col_offset, y, g, style);
+                    }
                 }
+            } else if i == rows / 3 && num_rows == 0 {
+                let msg = format!("kiroro editor -- version {}", VERSION);
+                let msg = truncate_width(&msg, cols.saturating_sub(1));
+                let padding_space_count = (cols - msg.len()) / 2;
+                let line = format!(
+                    "~{}{}",
+                    " ".repeat(padding_space_count.saturating_sub(1)),
+                    msg
+                );
+                screen.put(left, y, &line, Style::default());
+            } else if 0 < cols {
+                screen.put(left, y, "~", Style::default());
             }
-            // \1b[K is erase in line
-            // erase a line on current cursor
-            print!("\x1b[K");
-            print!("\r\n");
         });
-        self.out.flush().unwrap();
     }
 
     // TODO: buggy, fix it
     fn editor_scroll(&mut self) {
         self.cursor_x_to_render_x();
+        // keep the position scrolled by the mouse wheel
+        if self.scrolled {
+            return;
+        }
+        if self.config.settings.soft_wrap {
+            self.wrap_scroll();
+            return;
+        }
+        self.wrap_offset = 0;
 
         // vartical scroll
         if self.cursor_y < self.row_offset {
             self.row_offset = self.cursor_y;
         } else if self.row_offset + self.config.rows <= self.cursor_y {
-            self.row_offset += 1;
+            // window may be shrunk, keep the cursor in it
+            self.row_offset = (self.cursor_y + 1).saturating_sub(self.config.rows);
         }
 
         // horizontal scroll
-        if self.cursor_x < self.col_offset {
-            self.col_offset = self.cursor_x;
-        } else if self.col_offset + self.config.cols <= self.cursor_x {
-            self.col_offset += 1;
-        }
-
+        // render_x is a column on the screen, wide chars move it more than 1
         if self.render_x < self.col_offset {
             self.col_offset = self.render_x;
         } else if self.col_offset + self.config.cols <= self.render_x {
-            self.col_offset += 1;
+            self.col_offset = self.render_x - self.config.cols + 1;
         }
     }
 
-    fn draw_status_bar(&mut self) {
-        // \x1b[7m is reverse background and character color
-        print!("\x1b[7m");
-        // display filename
-        let noname = "[No Name]".to_string();
-        let filename = self.content.filename.as_ref();
-        let filename = match filename {
+    // scroll by screen lines to show the cursor.
+    // text is not scrolled horizontally in soft wrap mode.
+    fn wrap_scroll(&mut self) {
+        self.col_offset = 0;
+        let cols = self.config.cols;
+        let line = wrap_line(
+            &wrap_starts(&mut self.content, self.cursor_y, cols),
+            self.render_x,
+        );
+        if (self.cursor_y, line) < (self.row_offset, self.wrap_offset) {
+            self.row_offset = self.cursor_y;
+            self.wrap_offset = line;
+            return;
+        }
+
+        // the top of the window when the cursor is on the last line
+        let (mut row, mut line) = (self.cursor_y, line);
+        let mut remaining = self.config.rows.saturating_sub(1);
+        loop {
+            if remaining <= line {
+                line -= remaining;
+                break;
+            }
+            remaining -= line + 1;
+            if row == 0 {
+                line = 0;
+                break;
+            }
+            row -= 1;
+            line = wrap_starts(&mut self.content, row, cols).len() - 1;
+        }
+        if (self.row_offset, self.wrap_offset) < (row, line) {
+            self.row_offset = row;
+            self.wrap_offset = line;
+        }
+    }
+
+    // (screen line, column) of the cursor in the window in soft wrap mode
+    fn wrap_cursor_position(&mut self) -> (usize, usize) {
+        let cols = self.config.cols;
+        let mut y = 0;
+        for row in self.row_offset..self.cursor_y {
+            y += wrap_starts(&mut self.content, row, cols).len();
+        }
+        let starts = wrap_starts(&mut self.content, self.cursor_y, cols);
+        let line = wrap_line(&starts, self.render_x);
+
+        (
+            (y + line).saturating_sub(self.wrap_offset),
+            self.render_x - starts[line],
+        )
+    }
+
+    // move the cursor to the screen line above or below in soft wrap mode
+    fn move_wrapped_line(&mut self, up: bool) {
+        let cols = self.config.cols;
+        let render_x = self.current_render_x();
+        let starts = wrap_starts(&mut self.content, self.cursor_y, cols);
+        let line = wrap_line(&starts, render_x);
+        let x = render_x - starts[line];
+
+        let (row, starts, line) = if up && 0 < line {
+            (self.cursor_y, starts, line - 1)
+        } else if !up && line + 1 < starts.len() {
+            (self.cursor_y, starts, line + 1)
+        } else if up && 0 < self.cursor_y {
+            let starts = wrap_starts(&mut self.content, self.cursor_y - 1, cols);
+            let line = starts.len() - 1;
+            (self.cursor_y - 1, starts, line)
+        } else if !up && self.cursor_y < self.num_rows() {
+            let starts = wrap_starts(&mut self.content, self.cursor_y + 1, cols);
+            (self.cursor_y + 1, starts, 0)
+        } else {
+            return;
+        };
+        // keep the cursor in the screen line
+        let mut render_x = starts[line] + x;
+        if let Some(next) = starts.get(line + 1) {
+            render_x = render_x.min(next - 1);
+        }
+        self.cursor_y = row;
+        self.cursor_x = self
+            .current_row()
+            .map_or(0, |row| row.convert_index_render_to_raw(render_x));
+    }
+
+    fn draw_status_bar(
+        screen: &mut Screen,
+        content: &Content,
+        window: &Window,
+        dirty: bool,
+        active: bool,
+        search: Option<&SearchHighlight>,
+    ) {
+        // display filename
+        let noname = "[No Name]".to_string();
+        let filename = content.filename.as_ref();
+        let filename = match filename {
             Some(filename) => filename,
             None => &noname,
         };
-        let cursor_status = format!("{}/{}", self.cursor_y + 1, self.content.rows.len());
-        let edit_status = if self.dirty { "[modified]" } else { "" };
-
-        let spacer =
-            " ".repeat(self.config.cols - filename.len() - edit_status.len() - cursor_status.len());
+        // like `utf-8 CRLF BOM noeol | 3/10`
+        let mut file_format = format!("{} {}", content.encoding.name(), content.line_ending.name());
+        if content.bom {
+            file_format.push_str(" BOM");
+        }
+        if !content.final_newline {
+            file_format.push_str(" noeol");
+        }
+        let mut cursor_status = format!(
+            "{} | {}/{}",
+            file_format,
+            window.cursor_y + 1,
+            content.num_rows()
+        );
+        // like `match 3 of 17 | utf-8 LF | 3/10`
+        if let Some(search) = search {
+            cursor_status = format!("{} | {}", search.status(), cursor_status);
+        }
+        let edit_status = if dirty { "[modified]" } else { "" };
 
-        print!("{}{}{}{}", filename, edit_status, spacer, cursor_status);
-        // reset character attributes; change normal mode
-        print!("\x1b[m");
-        print!("\r\n");
+        let status_width = cursor_status.len() + edit_status.len();
+        let status = if status_width < window.cols {
+            let filename = truncate_width(filename, window.cols - status_width);
+            let spacer = " ".repeat(window.cols - filename.width() - status_width);
+            format!("{}{}{}{}", filename, edit_status, spacer, cursor_status)
+        } else {
+            // narrow window shows only filename
+            let filename = truncate_width(filename, window.cols);
+            format!("{}{}", filename, " ".repeat(window.cols - filename.width()))
+        };
 
-        self.out.flush().unwrap();
+        // reverse video, and bold marks the current window
+        let style = Style {
+            bold: active,
+            reverse: true,
+            ..Style::default()
+        };
+        screen.put(window.left, window.top + window.rows, &status, style);
     }
 
     fn draw_status_message_bar(&mut self) {
-        // clear message bar
-        print!("\x1b[K");
-
-        let len = self.status_message.len().min(self.config.cols);
-        let msg = &self.status_message[..len];
-        if (time::Instant::now() - self.status_message_time).as_secs() < 5 {
-            print!("{}", msg);
+        let msg = truncate_width(&self.status_message, self.config.screen_cols);
+        let elapsed = time::Instant::now() - self.status_message_time;
+        if elapsed.as_secs() < self.config.settings.message_timeout {
+            let y = self.config.screen_rows;
+            self.screen.put(0, y, msg, Style::default());
         }
-
-        self.out.flush().unwrap();
     }
 
     fn set_status_message<T>(&mut self, message: T)
@@ -495,28 +1355,283 @@ impl Editor {
         self.status_message_time = time::Instant::now();
     }
 
-    fn current_row(&self) -> Option<&Row> {
-        self.content.rows.get(self.cursor_y)
+    // reflect settings to all buffers
+    fn apply_settings(&mut self) {
+        let tab_stop = self.config.settings.tab_stop;
+        for content in std::iter::once(&mut self.content)
+            .chain(self.buffers.iter_mut().map(|buffer| &mut buffer.content))
+        {
+            content.set_tab_stop(content.settings.tab_stop.unwrap_or(tab_stop));
+        }
+    }
+
+    // settings of the current file by `.editorconfig`, then by its filetype section
+    fn resolve_file_settings(&mut self) {
+        let filename = match &self.content.filename {
+            Some(filename) => filename.clone(),
+            None => return,
+        };
+        let filetype = self.content.syntax.map(|syntax| syntax.filetype);
+        let editorconfig = if self.config.read_settings {
+            editorconfig::resolve(Path::new(&filename))
+        } else {
+            FileSettings::default()
+        };
+        self.content.settings = editorconfig.or(self.config.settings.filetype(filetype));
+        let tab_stop = self.content.settings.tab_stop;
+        self.content
+            .set_tab_stop(tab_stop.unwrap_or(self.config.settings.tab_stop));
+    }
+
+    // change settings by arguments like `tab_stop=8 expand_tabs`.
+    // show all settings without arguments.
+    fn set_option(&mut self, args: &str) -> error::Result<()> {
+        if args.trim().is_empty() {
+            self.set_status_message(self.config.settings.to_string());
+            return Ok(());
+        }
+
+        let mut shown = Vec::new();
+        for arg in args.split_whitespace() {
+            if let Some(value) = self.config.settings.apply(arg).map_err(Error::Config)? {
+                shown.push(value);
+            }
+        }
+        self.apply_settings();
+        if !shown.is_empty() {
+            self.set_status_message(shown.join(" "));
+        }
+
+        Ok(())
+    }
+
+    fn set_prompt(&mut self) -> error::Result<()> {
+        match self.prompt("set: ", None) {
+            Some(args) => self.set_option(&args),
+            None => Ok(()),
+        }
+    }
+
+    fn current_row(&mut self) -> Option<&Row> {
+        self.content.row_from_index(self.cursor_y)
     }
 
     fn cursor_x_to_render_x(&mut self) {
-        if let Some(row) = self.current_row() {
-            self.render_x = row.convert_index_raw_to_render(self.cursor_x);
+        self.render_x = self.current_render_x();
+    }
+
+    fn current_render_x(&mut self) -> usize {
+        let cursor_x = self.cursor_x;
+        self.current_row()
+            .map_or(0, |row| row.convert_index_raw_to_render(cursor_x))
+    }
+
+    fn insert_char(&mut self, c: char) -> error::Result<()> {
+        // Tab indents selected lines
+        if c == '\t' && self.selection().is_some() {
+            return self.indent_lines(true);
+        }
+        // typed char replaces selection
+        self.delete_selection()?;
+        if c == '\t' && self.expand_tabs() {
+            // spaces up to the next indent stop
+            let indent_size = self.indent_size();
+            let spaces = indent_size - self.current_render_x() % indent_size;
+            for _ in 0..spaces {
+                self.content
+                    .insert_char(self.cursor_y, self.cursor_x, ' ')?;
+                self.cursor_x += 1;
+            }
+        } else {
+            // closing bracket at the start of the line is dedented
+            let dedent = self.config.settings.auto_indent
+                && self
+                    .content
+                    .syntax
+                    .is_some_and(|syntax| syntax.dedent_before.contains(&c))
+                && self.content.line(self.cursor_y).is_some_and(|line| {
+                    line.get(..self.cursor_x)
+                        .is_some_and(|s| s.trim().is_empty())
+                });
+            self.content.insert_char(self.cursor_y, self.cursor_x, c)?;
+            self.cursor_x += c.len_utf8();
+            if dedent {
+                self.content.break_undo_group();
+                let (old, new) = self.outdent_row(self.cursor_y)?;
+                self.cursor_x = self.cursor_x + new - old;
+            }
         }
+        self.dirty = true;
+
+        Ok(())
     }
 
-    fn insert_char(&mut self, c: char) -> Result<(), Box<dyn error::Error>> {
-        self.content.insert_char(self.cursor_y, self.cursor_x, c)?;
-        self.cursor_x += 1;
+    // position of the bracket matching the one at the cursor, or just before the cursor
+    fn matching_bracket(&mut self) -> Option<(usize, usize)> {
+        let (row, col) = (self.cursor_y, self.cursor_x);
+        if let Some(pos) = self.content.matching_bracket((row, col)) {
+            return Some(pos);
+        }
+        let prev = self.current_row()?.prev_boundary(col);
+        if prev < col {
+            self.content.matching_bracket((row, prev))
+        } else {
+            None
+        }
+    }
+
+    // chars making words with alphanumerics, by the file settings or the global ones
+    pub(super) fn word_chars(&self) -> &str {
+        self.content
+            .settings
+            .word_chars
+            .as_deref()
+            .unwrap_or(&self.config.settings.word_chars)
+    }
+
+    fn expand_tabs(&self) -> bool {
+        self.content
+            .settings
+            .expand_tabs
+            .unwrap_or(self.config.settings.expand_tabs)
+    }
+
+    // width of a level of indent
+    fn indent_size(&self) -> usize {
+        self.content
+            .settings
+            .indent_size
+            .unwrap_or(self.content.tab_stop())
+    }
+
+    // width of leading whitespace on the screen
+    fn indent_width(&self, indent: &str) -> usize {
+        let tab_stop = self.content.tab_stop();
+        indent.chars().fold(0, |width, c| match c {
+            '\t' => width + tab_stop - width % tab_stop,
+            _ => width + 1,
+        })
+    }
+
+    // whitespace of the width, tabs are used unless expand_tabs
+    fn make_indent(&self, width: usize) -> String {
+        if self.expand_tabs() {
+            " ".repeat(width)
+        } else {
+            let tab_stop = self.content.tab_stop();
+            format!(
+                "{}{}",
+                "\t".repeat(width / tab_stop),
+                " ".repeat(width % tab_stop)
+            )
+        }
+    }
+
+    // replace leading whitespace of the row with the width returned by f.
+    // return the old and new length of the whitespace.
+    fn reindent_row<F>(&mut self, row: usize, f: F) -> error::Result<(usize, usize)>
+    where
+        F: Fn(usize) -> usize,
+    {
+        let line = self.content.line(row).unwrap_or_default();
+        let old = line.len() - line.trim_start().len();
+        let indent = self.make_indent(f(self.indent_width(&line[..old])));
+        if indent != line[..old] {
+            self.content.replace(row, 0, old, &indent)?;
+        }
+
+        Ok((old, indent.len()))
+    }
+
+    // remove a level of indent from the row
+    fn outdent_row(&mut self, row: usize) -> error::Result<(usize, usize)> {
+        let size = self.indent_size();
+        self.reindent_row(row, |width| width.saturating_sub(1) / size * size)
+    }
+
+    // indent or outdent the current line or the selected lines by a level
+    fn indent_lines(&mut self, indent: bool) -> error::Result<()> {
+        let rows = match self.selection() {
+            // the row where the selection ends at its start is not selected
+            Some((start, end)) if start.0 < end.0 && end.1 == 0 => start.0..end.0,
+            Some((start, end)) => start.0..end.0 + 1,
+            None => self.cursor_y..self.cursor_y + 1,
+        };
+        let size = self.indent_size();
+
+        self.content.begin_undo_group();
+        let mut res = Ok(());
+        for row in rows.start..rows.end.min(self.num_rows()) {
+            let blank = self
+                .content
+                .line(row)
+                .is_some_and(|line| line.trim().is_empty());
+            let indented = if indent && blank {
+                // blank lines are not indented
+                continue;
+            } else if indent {
+                self.reindent_row(row, |width| (width / size + 1) * size)
+            } else {
+                self.outdent_row(row)
+            };
+            let (old, new) = match indented {
+                Ok(indented) => indented,
+                Err(e) => {
+                    res = Err(e);
+                    break;
+                }
+            };
+            // keep the cursor and the mark on the same text
+            let shift = |col: usize| {
+                if old <= col {
+                    col + new - old
+                } else {
+                    col.min(new)
+                }
+            };
+            if row == self.cursor_y {
+                self.cursor_x = shift(self.cursor_x);
+            }
+            if let Some(mark) = self.mark.as_mut().filter(|mark| mark.row == row) {
+                mark.col = shift(mark.col);
+            }
+        }
+        self.content.end_undo_group();
+        self.dirty = true;
+
+        res
+    }
+
+    // delete the char at the cursor, or join the next row at the end of the row
+    fn delete_forward_char(&mut self) -> error::Result<()> {
+        let cursor = (self.cursor_y, self.cursor_x);
+        let len = self.content.line(cursor.0).map_or(0, |line| line.len());
+        if cursor.1 < len {
+            self.content.delete_char(cursor.0, cursor.1, cursor)?;
+        } else if cursor.0 + 1 < self.num_rows() {
+            self.content
+                .concatenate_previous_row(cursor.0 + 1, cursor)?;
+        } else {
+            return Ok(());
+        }
         self.dirty = true;
 
         Ok(())
     }
 
-    fn backspace_char(&mut self) -> Result<(), Box<dyn error::Error>> {
-        if let Some(col_idx) = self.cursor_x.checked_sub(1) {
-            self.content.delete_char(self.cursor_y, col_idx)?;
-            self.cursor_x -= 1;
+    fn backspace_char(&mut self) -> error::Result<()> {
+        if self.selection().is_some() {
+            return self.delete_selection();
+        }
+
+        if 0 < self.cursor_x {
+            let cursor_x = self.cursor_x;
+            let col_idx = self
+                .current_row()
+                .map_or(0, |row| row.prev_boundary(cursor_x));
+            self.content
+                .delete_char(self.cursor_y, col_idx, (self.cursor_y, cursor_x))?;
+            self.cursor_x = col_idx;
             self.dirty = true;
         } else {
             // case of first line, there is no previous string.
@@ -527,10 +1642,10 @@ impl Editor {
 
             let cursor_x = self
                 .content
-                .rows
-                .get(self.cursor_y - 1)
-                .map_or(0, |r| r.raw.len());
-            self.content.concatenate_previous_row(self.cursor_y)?;
+                .line(self.cursor_y - 1)
+                .map_or(0, |line| line.len());
+            self.content
+                .concatenate_previous_row(self.cursor_y, (self.cursor_y, 0))?;
             self.dirty = true;
             self.cursor_y -= 1;
             self.cursor_x = cursor_x;
@@ -539,21 +1654,309 @@ impl Editor {
         Ok(())
     }
 
-    fn insert_new_line(&mut self) -> Result<(), Box<dyn error::Error>> {
-        self.content.insert_new_line(self.cursor_y, self.cursor_x)?;
+    // delete back to the start of the word, or forward to the start of the next word.
+    // chars are deleted one by one from the end, in an undo step.
+    fn delete_word(&mut self, forward: bool) -> error::Result<()> {
+        if self.selection().is_some() {
+            return self.delete_selection();
+        }
 
-        self.cursor_y += 1;
-        self.cursor_x = 0;
+        let pos = (self.cursor_y, self.cursor_x);
+        let mut scanner = Scanner::new(&self.content, self.word_chars());
+        let (start, end) = if forward {
+            (pos, scanner.word_forward(pos))
+        } else {
+            (scanner.word_backward(pos), pos)
+        };
+        (self.cursor_y, self.cursor_x) = end;
+        self.content.begin_undo_group();
+        let mut res = Ok(());
+        while res.is_ok() && start < (self.cursor_y, self.cursor_x) {
+            res = self.backspace_char();
+        }
+        self.content.end_undo_group();
+
+        res
+    }
+
+    fn insert_new_line(&mut self) -> error::Result<()> {
+        self.delete_selection()?;
+        if !self.config.settings.auto_indent || self.num_rows() <= self.cursor_y {
+            self.content.insert_new_line(self.cursor_y, self.cursor_x)?;
+            self.cursor_y += 1;
+            self.cursor_x = 0;
+            self.dirty = true;
+            return Ok(());
+        }
+
+        self.content.begin_undo_group();
+        let res = self.insert_indented_line();
+        self.content.end_undo_group();
         self.dirty = true;
 
+        res
+    }
+
+    // the new line keeps the indent of the current line,
+    // and is indented more after an opening bracket of the filetype
+    fn insert_indented_line(&mut self) -> error::Result<()> {
+        let (row, mut x) = (self.cursor_y, self.cursor_x);
+        let line = self.content.line(row).unwrap_or_default();
+        // whitespace moved to the new line is replaced with the indent
+        let after = &line[x.min(line.len())..];
+        let spaces = after.len() - after.trim_start().len();
+        if 0 < spaces {
+            self.content.replace(row, x, x + spaces, "")?;
+        }
+        // line of only indent is left empty
+        let before = &line[..x.min(line.len())];
+        if before.trim().is_empty() && !before.is_empty() {
+            self.content.replace(row, 0, x, "")?;
+            x = 0;
+        }
+
+        let indent = &before[..before.len() - before.trim_start().len()];
+        let (indent_after, dedent_before) =
+            self.content.syntax.map_or((&[][..], &[][..]), |syntax| {
+                (syntax.indent_after, syntax.dedent_before)
+            });
+        let opens = before
+            .trim_end()
+            .chars()
+            .next_back()
+            .is_some_and(|c| indent_after.contains(&c));
+        let closes = opens
+            && after
+                .trim_start()
+                .chars()
+                .next()
+                .is_some_and(|c| dedent_before.contains(&c));
+        let new_indent = if opens {
+            let width = self.indent_width(indent) + self.indent_size();
+            self.make_indent(width)
+        } else {
+            indent.to_string()
+        };
+
+        self.content.insert_new_line(row, x)?;
+        self.content.replace(row + 1, 0, 0, &new_indent)?;
+        // closing bracket goes to the next line with the original indent
+        if closes {
+            self.content.insert_new_line(row + 1, new_indent.len())?;
+            self.content.replace(row + 2, 0, 0, indent)?;
+        }
+        self.cursor_y = row + 1;
+        self.cursor_x = new_indent.len();
+
         Ok(())
     }
 
-    fn save(&mut self) -> Result<(), Box<dyn error::Error>> {
+    fn toggle_mark(&mut self) {
+        if self.mark.take().is_some() {
+            self.set_status_message("mark deactivated");
+        } else {
+            self.mark = Some(Mark {
+                row: self.cursor_y,
+                col: self.cursor_x,
+                shift: false,
+            });
+            self.set_status_message("mark set");
+        }
+    }
+
+    // called before cursor moves.
+    // shift+arrow starts selection, arrow without shift ends it.
+    fn update_mark(&mut self, shift: bool) {
+        match self.mark {
+            None if shift => {
+                self.mark = Some(Mark {
+                    row: self.cursor_y,
+                    col: self.cursor_x,
+                    shift: true,
+                });
+            }
+            Some(mark) if mark.shift && !shift => self.mark = None,
+            _ => {}
+        }
+    }
+
+    // return ordered (start, end) positions of non-empty selection
+    fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let mark = self.mark?;
+        let mark = (mark.row, mark.col);
+        let cursor = (self.cursor_y, self.cursor_x);
+
+        let (start, end) = match mark.cmp(&cursor) {
+            Ordering::Less => (mark, cursor),
+            Ordering::Greater => (cursor, mark),
+            Ordering::Equal if self.is_visual_mode() => (cursor, cursor),
+            Ordering::Equal => return None,
+        };
+        // visual mode selects the char under cursor too
+        if self.is_visual_mode() {
+            let end = Scanner::new(&self.content, self.word_chars()).char_end(end);
+            return Some((start, end)).filter(|(start, end)| start != end);
+        }
+
+        Some((start, end))
+    }
+
+    // delete selected text if any
+    fn delete_selection(&mut self) -> error::Result<()> {
+        if let Some((start, end)) = self.selection() {
+            self.content.delete_range(start, end)?;
+            (self.cursor_y, self.cursor_x) = start;
+            self.dirty = true;
+        }
+        self.mark = None;
+
+        Ok(())
+    }
+
+    fn copy(&mut self) {
+        match self.selection() {
+            Some((start, end)) => {
+                let text = self.content.text_range(start, end);
+                self.kill(text);
+                self.mark = None;
+                self.set_status_message("copied");
+            }
+            None => self.set_status_message("no selection"),
+        }
+    }
+
+    fn cut(&mut self) -> error::Result<()> {
+        match self.selection() {
+            Some((start, end)) => {
+                let text = self.content.text_range(start, end);
+                self.kill(text);
+                self.content.begin_undo_group();
+                let res = self.delete_selection();
+                self.content.end_undo_group();
+                res
+            }
+            None => {
+                self.set_status_message("no selection");
+                Ok(())
+            }
+        }
+    }
+
+    // push text to kill ring and system clipboard
+    fn kill(&mut self, text: String) {
+        if self.config.osc52 {
+            // \x1b]52;c;{base64}\x07 sets clipboard of the terminal
+            let osc52 = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+            let _ = self.terminal.write(&osc52);
+        }
+        self.kill_ring.push(text);
+        if KILL_RING_CAPACITY < self.kill_ring.len() {
+            self.kill_ring.remove(0);
+        }
+    }
+
+    fn paste(&mut self) -> error::Result<()> {
+        match self.kill_ring.len() {
+            0 => {
+                self.set_status_message("kill ring is empty");
+                Ok(())
+            }
+            len => self.yank(len - 1),
+        }
+    }
+
+    // insert kill_ring[index] at cursor replacing selection
+    fn yank(&mut self, index: usize) -> error::Result<()> {
+        self.content.begin_undo_group();
+        let res = self.delete_selection().and_then(|_| {
+            let text = &self.kill_ring[index];
+            self.content.insert_text(self.cursor_y, self.cursor_x, text)
+        });
+        self.content.end_undo_group();
+
+        let start = (self.cursor_y, self.cursor_x);
+        let end = res?;
+        (self.cursor_y, self.cursor_x) = end;
+        self.dirty = true;
+        self.last_yank = Some(Yank { start, end, index });
+
+        Ok(())
+    }
+
+    // replace text pasted just before with the previous one in kill ring
+    fn yank_pop(&mut self, last_yank: Option<Yank>) -> error::Result<()> {
+        let yank = match last_yank {
+            Some(yank) => yank,
+            None => {
+                self.set_status_message("previous command was not paste");
+                return Ok(());
+            }
+        };
+
+        let index = (yank.index + self.kill_ring.len() - 1) % self.kill_ring.len();
+        // replace the previous paste with the previous text in one undo step
+        self.content.begin_undo_group();
+        let res = self
+            .content
+            .delete_range(yank.start, yank.end)
+            .and_then(|_| {
+                (self.cursor_y, self.cursor_x) = yank.start;
+                self.mark = None;
+                self.yank(index)
+            });
+        self.content.end_undo_group();
+
+        res
+    }
+
+    // convert line ending between LF and CRLF
+    fn toggle_line_ending(&mut self) {
+        let line_ending = match self.content.line_ending {
+            LineEnding::Lf => LineEnding::CrLf,
+            LineEnding::CrLf => LineEnding::Lf,
+        };
+        self.content.set_line_ending(line_ending);
+        self.dirty = self.content.is_modified();
+        self.set_status_message(format!("line ending: {}", line_ending.name()));
+    }
+
+    fn undo(&mut self) -> error::Result<()> {
+        self.mark = None;
+        match self.content.undo()? {
+            Some((row, col)) => {
+                self.cursor_y = row;
+                self.cursor_x = col;
+                self.dirty = self.content.is_modified();
+            }
+            None => self.set_status_message("nothing to undo"),
+        }
+
+        Ok(())
+    }
+
+    fn redo(&mut self) -> error::Result<()> {
+        self.mark = None;
+        match self.content.redo()? {
+            Some((row, col)) => {
+                self.cursor_y = row;
+                self.cursor_x = col;
+                self.dirty = self.content.is_modified();
+            }
+            None => self.set_status_message("nothing to redo"),
+        }
+
+        Ok(())
+    }
+
+    fn save(&mut self) -> error::Result<()> {
         self.set_status_message("save mode");
         if self.content.is_phantom() {
             match self.prompt("save as: ", None) {
-                Some(input) => self.content.filename = Some(input),
+                Some(input) => {
+                    self.content.filename = Some(input);
+                    self.content.select_syntax();
+                    self.resolve_file_settings();
+                }
                 None => {
                     self.set_status_message("save aborted");
                     return Ok(());
@@ -561,65 +1964,110 @@ impl Editor {
             }
         };
 
-        let rows = self.content.rows_to_string();
+        self.content.apply_save_settings()?;
+        // trailing whitespace under cursor may be removed
+        self.clamp_cursor();
         if let Some(name) = &self.content.filename {
-            let mut f = OpenOptions::new().write(true).create(true).open(name)?;
-            f.set_len(rows.len() as u64)?;
-            f.write(rows.as_bytes())?;
-            let msg = format!("saved into {}", name);
+            let content = &self.content;
+            let written = file::write_atomic(Path::new(name), self.config.backup, |out| {
+                content.write_to(out)
+            })?;
+            let msg = format!("{} bytes written to {}", written, name);
             self.set_status_message(msg);
+            self.content.mark_saved();
             self.dirty = false;
         }
 
-        return Ok(());
+        Ok(())
     }
 
     fn num_rows(&self) -> usize {
-        self.content.rows.len()
+        self.content.num_rows()
     }
 
     fn prompt(
         &mut self,
         prompt: &str,
-        callback: Option<fn(&mut Self, &String, &event::Key)>,
+        callback: Option<fn(&mut Self, &str, &event::Key)>,
     ) -> Option<String> {
+        self.prompt_with(|_, buf| format!("{}{}", prompt, buf), callback, false)
+    }
+
+    // message is made from the input each time a key is pressed.
+    // empty input is accepted by Enter only if allow_empty is true.
+    fn prompt_with<F>(
+        &mut self,
+        message: F,
+        callback: Option<fn(&mut Self, &str, &event::Key)>,
+        allow_empty: bool,
+    ) -> Option<String>
+    where
+        F: Fn(&Self, &str) -> String,
+    {
         let mut buf = String::with_capacity(128);
 
         loop {
-            let msg = format!("{}{}", prompt, buf);
+            let msg = message(self, &buf);
             self.set_status_message(msg);
             self.refresh_screen();
 
-            let key = self.read_key();
+            // closed input cancels the prompt
+            let key = self.read_key().unwrap_or(event::Key::Esc);
 
             match &key {
                 // Enter
-                event::Key::Char('\n') | event::Key::Char('\r') => {
-                    if !buf.is_empty() {
-                        callback.map(|cb| cb(self, &buf, &key));
-                        return Some(buf);
+                event::Key::Char('\n') | event::Key::Char('\r')
+                    if allow_empty || !buf.is_empty() =>
+                {
+                    if let Some(cb) = callback {
+                        cb(self, &buf, &key);
                     }
+                    return Some(buf);
                 }
                 event::Key::Delete | event::Key::Backspace | event::Key::Ctrl('l') => {
                     buf.pop();
                 }
                 // cancel the input prompt
                 event::Key::Esc => {
-                    callback.map(|cb| cb(self, &buf, &key));
+                    if let Some(cb) = callback {
+                        cb(self, &buf, &key);
+                    }
                     return None;
                 }
                 event::Key::Char(c) => buf.push(*c),
                 _ => {}
             };
 
-            callback.map(|cb| cb(self, &buf, &key));
+            if let Some(cb) = callback {
+                cb(self, &buf, &key);
+            }
+        }
+    }
+
+    // like `find [regex icase]: `
+    fn search_prompt(&self, name: &str, buf: &str) -> String {
+        format!("{}{}: {}", name, self.search_options.label(), buf)
+    }
+
+    // Alt-R: regex, Alt-I: ignore case, Alt-W: whole word.
+    // return true if the key toggles an option.
+    fn toggle_search_option(&mut self, key: &event::Key) -> bool {
+        let options = &mut self.search_options;
+        match key {
+            event::Key::Alt('r') => options.regex = !options.regex,
+            event::Key::Alt('i') => options.ignore_case = !options.ignore_case,
+            event::Key::Alt('w') => options.whole_word = !options.whole_word,
+            _ => return false,
         }
+
+        true
     }
 
-    fn find_callback(&mut self, query: &String, key: &event::Key) {
+    fn find_callback(&mut self, query: &str, key: &event::Key) {
         let direction = match key {
             // leave search mode by enter or escape
             event::Key::Char('\n') | event::Key::Char('\r') | event::Key::Esc => {
+                self.search = None;
                 self.set_status_message("find aborted");
                 return;
             }
@@ -631,17 +2079,36 @@ impl Editor {
                 // backward
                 SearchDirection::Backward
             }
-            _ => SearchDirection::None,
+            k => {
+                self.toggle_search_option(k);
+                SearchDirection::None
+            }
         };
 
-        if let Some((row, col)) = self
+        // regex may be incomplete while typing
+        let pattern = match Pattern::new(query, &self.search_options) {
+            Ok(pattern) if !query.is_empty() => pattern,
+            _ => {
+                self.search = None;
+                return;
+            }
+        };
+        let current = self
             .content
-            .find(query, self.cursor_y, self.cursor_x, &direction)
-        {
-            self.cursor_x = col;
+            .find(&pattern, self.cursor_y, self.cursor_x, &direction);
+        if let Some((row, start, _)) = current {
+            self.cursor_x = start;
             self.cursor_y = row;
             self.row_offset = self.num_rows();
         }
+        let count = self
+            .content
+            .count_matches(&pattern, self.cursor_y, self.cursor_x);
+        self.search = Some(SearchHighlight {
+            pattern,
+            current,
+            count,
+        });
     }
 
     fn find(&mut self) {
@@ -650,7 +2117,11 @@ impl Editor {
         let saved_row_offset = self.row_offset;
         let saved_col_offset = self.col_offset;
 
-        let query = self.prompt("find as: ", Some(Self::find_callback));
+        let query = self.prompt_with(
+            |editor, buf| editor.search_prompt("find", buf),
+            Some(Self::find_callback),
+            false,
+        );
         // when user presses Esc, query is None.
         if query.is_none() {
             self.cursor_x = saved_cursor_x;
@@ -659,4 +2130,196 @@ impl Editor {
             self.col_offset = saved_col_offset;
         }
     }
+
+    fn replace_callback(&mut self, _query: &str, key: &event::Key) {
+        self.toggle_search_option(key);
+    }
+
+    // replace matches from the top of the file, asking for each match.
+    // all replacements are undone at once.
+    fn replace(&mut self) -> error::Result<()> {
+        let query = match self.prompt_with(
+            |editor, buf| editor.search_prompt("replace", buf),
+            Some(Self::replace_callback),
+            false,
+        ) {
+            Some(query) => query,
+            None => {
+                self.set_status_message("replace aborted");
+                return Ok(());
+            }
+        };
+        let pattern = Pattern::new(&query, &self.search_options)?;
+        let replacement = match self.prompt_with(
+            |_, buf| format!("replace {} with: {}", query, buf),
+            None,
+            true,
+        ) {
+            Some(replacement) => replacement,
+            None => {
+                self.set_status_message("replace aborted");
+                return Ok(());
+            }
+        };
+
+        let saved_cursor_x = self.cursor_x;
+        let saved_cursor_y = self.cursor_y;
+        let mut replaced = 0;
+        let mut replace_all = false;
+        // search position, a match just at it is skipped after an empty match
+        let (mut row_idx, mut col_idx, mut inclusive) = (0, 0, true);
+
+        self.content.begin_undo_group();
+        let res = loop {
+            let (row, start, end) = match self
+                .content
+                .find_from(&pattern, row_idx, col_idx, inclusive)
+            {
+                Some(found) => found,
+                None => break Ok(()),
+            };
+            self.cursor_x = start;
+            self.cursor_y = row;
+
+            if !replace_all {
+                let count = self.content.count_matches(&pattern, row, start);
+                self.search = Some(SearchHighlight {
+                    pattern: pattern.clone(),
+                    current: Some((row, start, end)),
+                    count,
+                });
+                self.set_status_message("replace? (y)es / (n)o / (a)ll / (q)uit");
+                self.refresh_screen();
+                let key = match self.read_key() {
+                    Ok(key) => key,
+                    Err(e) => break Err(e),
+                };
+                self.search = None;
+                match key {
+                    event::Key::Char('y') => {}
+                    event::Key::Char('a') => replace_all = true,
+                    event::Key::Char('n') => {
+                        (row_idx, col_idx, inclusive) = (row, end, start != end);
+                        continue;
+                    }
+                    event::Key::Char('q') | event::Key::Esc => break Ok(()),
+                    _ => continue,
+                }
+            }
+
+            let line = self.content.line(row).unwrap_or_default();
+            let text = pattern.replacement(&line, start, &replacement);
+            if let Err(e) = self.content.replace(row, start, end, &text) {
+                break Err(e);
+            }
+            replaced += 1;
+            self.cursor_x = start + text.len();
+            (row_idx, col_idx, inclusive) = (row, start + text.len(), start != end);
+        };
+        self.content.end_undo_group();
+        self.dirty = self.content.is_modified();
+
+        if replaced == 0 {
+            self.cursor_x = saved_cursor_x;
+            self.cursor_y = saved_cursor_y;
+        }
+        self.set_status_message(format!("{} replaced", replaced));
+
+        res
+    }
+}
+
+// return the longest prefix of s which fits in width columns
+fn truncate_width(s: &str, width: usize) -> &str {
+    let mut end = 0;
+    let mut total = 0;
+    for (i, g) in s.grapheme_indices(true) {
+        total += g.width();
+        if width < total {
+            break;
+        }
+        end = i + g.len();
+    }
+
+    &s[..end]
+}
+
+// encode bytes in base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(TABLE[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+// render indices where screen lines of the row start, see Row::wrap_starts.
+// a row out of the content is a screen line.
+fn wrap_starts(content: &mut Content, row: usize, width: usize) -> Vec<usize> {
+    content
+        .row_from_index(row)
+        .map_or(vec![0], |row| row.wrap_starts(width))
+}
+
+// index of the screen line containing render_x
+fn wrap_line(starts: &[usize], render_x: usize) -> usize {
+    starts
+        .iter()
+        .rposition(|start| *start <= render_x)
+        .unwrap_or(0)
+}
+
+// (row idx, screen line in the row, start and end render index) of screen lines
+// from the wrap_offset-th line of the row at row_offset
+fn wrap_lines(
+    content: &mut Content,
+    row_offset: usize,
+    wrap_offset: usize,
+    rows: usize,
+    cols: usize,
+) -> Vec<(usize, usize, usize, usize)> {
+    let mut lines = Vec::with_capacity(rows);
+    let mut skip = wrap_offset;
+    for row in row_offset.. {
+        let starts = wrap_starts(content, row, cols);
+        for (i, start) in starts.iter().enumerate().skip(skip) {
+            let end = starts.get(i + 1).copied().unwrap_or(start + cols);
+            lines.push((row, i, *start, end));
+            if rows <= lines.len() {
+                return lines;
+            }
+        }
+        skip = 0;
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64("あ\n".as_bytes()), "44GCCg==");
+        assert_eq!(base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+    }
 }
@@ -0,0 +1,199 @@
+use std::time;
+
+use termion::event::{MouseButton, MouseEvent};
+
+use super::{wrap_lines, Editor, Mark};
+use crate::motion::Scanner;
+
+// second click within this time at the same position selects a word
+const DOUBLE_CLICK_MILLIS: u64 = 500;
+// rows scrolled by a notch of the wheel
+const WHEEL_SCROLL_ROWS: usize = 3;
+
+// the last press of the left button
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Click {
+    // (row idx, col idx) of the cursor placed by the click
+    pos: (usize, usize),
+    time: time::Instant,
+}
+
+// 0-origin cell of 1-origin coordinates of termion.
+// X10 reports may give 0 for positions out of their range.
+fn cell(x: u16, y: u16) -> (usize, usize) {
+    (
+        usize::from(x).saturating_sub(1),
+        usize::from(y).saturating_sub(1),
+    )
+}
+
+impl Editor {
+    pub(super) fn mouse(&mut self, event: MouseEvent) {
+        // pasted text may be out of sight
+        self.last_yank = None;
+        match event {
+            MouseEvent::Press(MouseButton::Left, x, y) => {
+                let (x, y) = cell(x, y);
+                self.click(x, y);
+            }
+            MouseEvent::Hold(x, y) if self.dragging => {
+                let (x, y) = cell(x, y);
+                self.drag(x, y);
+            }
+            MouseEvent::Release(..) => self.dragging = false,
+            MouseEvent::Press(MouseButton::WheelUp, x, y) => {
+                let (x, y) = cell(x, y);
+                self.scroll_wheel(x, y, false);
+            }
+            MouseEvent::Press(MouseButton::WheelDown, x, y) => {
+                let (x, y) = cell(x, y);
+                self.scroll_wheel(x, y, true);
+            }
+            _ => {}
+        }
+    }
+
+    // place the cursor, or select the word by double click
+    fn click(&mut self, x: usize, y: usize) {
+        let Some(n) = self.window_at(x, y) else {
+            return;
+        };
+        self.focus_window(n);
+        self.mark = None;
+        self.scrolled = false;
+        let window = &self.windows[n];
+        // status bar only focuses the window
+        if window.top + window.rows <= y {
+            self.last_click = None;
+            return;
+        }
+
+        let pos = self.position_at(x, y);
+        (self.cursor_y, self.cursor_x) = pos;
+        self.dragging = true;
+        let double_click = self.last_click.is_some_and(|click| {
+            click.pos == pos
+                && click.time.elapsed() < time::Duration::from_millis(DOUBLE_CLICK_MILLIS)
+        });
+        if double_click {
+            let (start, end) = Scanner::new(&self.content, self.word_chars()).word_at(pos);
+            self.mark = Some(Mark {
+                row: start.0,
+                col: start.1,
+                shift: true,
+            });
+            (self.cursor_y, self.cursor_x) = end;
+            self.last_click = None;
+        } else {
+            self.last_click = Some(Click {
+                pos,
+                time: time::Instant::now(),
+            });
+        }
+    }
+
+    // select from the clicked position to the pointer.
+    // dragging out of the window scrolls it.
+    fn drag(&mut self, x: usize, y: usize) {
+        if self.mark.is_none() {
+            self.mark = Some(Mark {
+                row: self.cursor_y,
+                col: self.cursor_x,
+                // like shift+arrow, cleared by arrow keys
+                shift: true,
+            });
+        }
+        self.last_click = None;
+        self.scrolled = false;
+        (self.cursor_y, self.cursor_x) = self.position_at(x, y);
+    }
+
+    // scroll the window under the pointer without moving the cursor
+    fn scroll_wheel(&mut self, x: usize, y: usize, down: bool) {
+        let Some(n) = self.window_at(x, y) else {
+            return;
+        };
+        let window = &self.windows[n];
+        let num_rows = if n == self.current_window || window.buffer == self.current_buffer {
+            self.content.num_rows()
+        } else {
+            self.buffers[window.buffer].content.num_rows()
+        };
+        let row_offset = if n == self.current_window {
+            self.row_offset
+        } else {
+            window.row_offset
+        };
+        let row_offset = if down {
+            (row_offset + WHEEL_SCROLL_ROWS).min(num_rows.saturating_sub(1))
+        } else {
+            row_offset.saturating_sub(WHEEL_SCROLL_ROWS)
+        };
+
+        if n == self.current_window {
+            self.row_offset = row_offset;
+            self.wrap_offset = 0;
+            // the cursor is brought back by the next key
+            self.scrolled = true;
+        } else {
+            self.windows[n].row_offset = row_offset;
+            self.windows[n].wrap_offset = 0;
+        }
+    }
+
+    // window containing the screen position, including its status bar
+    fn window_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.windows.iter().position(|window| {
+            (window.left..window.left + window.cols).contains(&x)
+                && (window.top..=window.top + window.rows).contains(&y)
+        })
+    }
+
+    // (row idx, col idx) of the text at the screen position in the current window.
+    // positions out of the window are clamped to the rows above or below it.
+    fn position_at(&mut self, x: usize, y: usize) -> (usize, usize) {
+        let window = &self.windows[self.current_window];
+        let cols = window.text_cols();
+        let x = x.saturating_sub(window.left + window.gutter);
+        let num_rows = self.content.num_rows();
+
+        let (row, render_x) = if self.config.settings.soft_wrap {
+            let lines = wrap_lines(
+                &mut self.content,
+                self.row_offset,
+                self.wrap_offset,
+                window.rows.max(1),
+                cols,
+            );
+            let line = y
+                .saturating_sub(window.top)
+                .min(lines.len().saturating_sub(1));
+            match lines.get(line) {
+                Some(&(row, _, start, end)) => {
+                    // click after a wrapped line is on its last column
+                    (row, start + x.min(end.saturating_sub(start + 1)))
+                }
+                None => (self.row_offset, x),
+            }
+        } else {
+            let row = if y < window.top {
+                // above the window while dragging
+                self.row_offset.saturating_sub(1)
+            } else {
+                (self.row_offset + y - window.top).min(self.row_offset + window.rows)
+            };
+            (row, self.col_offset + x)
+        };
+
+        // below the last row
+        if num_rows <= row {
+            return (num_rows, 0);
+        }
+        let col = self
+            .content
+            .row_from_index(row)
+            .map_or(0, |row| row.convert_index_render_to_raw(render_x));
+
+        (row, col)
+    }
+}
@@ -0,0 +1,823 @@
+use termion::event;
+
+use super::{Editor, Mark};
+use crate::error::{self, Error};
+use crate::motion::Scanner;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    Insert,
+    Visual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    LineStart,
+    LineEnd,
+    // gg, G
+    FirstLine,
+    LastLine,
+    // f<char>
+    FindChar(char),
+    // %
+    MatchBracket,
+    // }, {
+    ParagraphForward,
+    ParagraphBackward,
+    // dd, cc, yy
+    Line,
+}
+
+impl Motion {
+    fn is_linewise(&self) -> bool {
+        matches!(
+            self,
+            Self::Up | Self::Down | Self::FirstLine | Self::LastLine | Self::Line
+        )
+    }
+
+    // the char at the target is included in the operator range
+    fn is_inclusive(&self) -> bool {
+        matches!(self, Self::WordEnd | Self::FindChar(_) | Self::MatchBracket)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    // i, a, I, A
+    Insert,
+    Append,
+    InsertLineStart,
+    AppendLineEnd,
+    // o, O
+    OpenBelow,
+    OpenAbove,
+    // x
+    DeleteChar,
+    // p, P
+    PasteAfter,
+    PasteBefore,
+    Undo,
+    Visual,
+    // .
+    Repeat,
+    // :
+    CommandLine,
+}
+
+impl Action {
+    fn is_change(&self) -> bool {
+        !matches!(
+            self,
+            Self::Undo | Self::Visual | Self::Repeat | Self::CommandLine
+        )
+    }
+
+    // text typed until Esc is a part of the change
+    fn enters_insert_mode(&self) -> bool {
+        matches!(
+            self,
+            Self::Insert
+                | Self::Append
+                | Self::InsertLineStart
+                | Self::AppendLineEnd
+                | Self::OpenBelow
+                | Self::OpenAbove
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Move(Option<usize>, Motion),
+    Operate(Operator, Option<usize>, Motion),
+    Act(Option<usize>, Action),
+}
+
+impl Command {
+    // the command with the count replaced, like `3.`
+    fn with_count(self, count: usize) -> Self {
+        match self {
+            Self::Move(_, motion) => Self::Move(Some(count), motion),
+            Self::Operate(operator, _, motion) => Self::Operate(operator, Some(count), motion),
+            Self::Act(_, action) => Self::Act(Some(count), action),
+        }
+    }
+}
+
+enum Parse<T> {
+    // more keys are needed
+    Pending,
+    Invalid,
+    Done(T),
+}
+
+// the last change, repeated by `.`
+#[derive(Debug, Clone)]
+struct Change {
+    command: Command,
+    // keys typed in insert mode entered by the command
+    keys: Vec<event::Key>,
+}
+
+#[derive(Debug)]
+pub struct Vim {
+    pub mode: Mode,
+    // keys typed in normal or visual mode which are not a command yet
+    pending: Vec<char>,
+    last_change: Option<Change>,
+    // change entering insert mode, saved as last_change when insert mode ends
+    recording: Option<Change>,
+}
+
+impl Default for Vim {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Normal,
+            pending: Vec::new(),
+            last_change: None,
+            recording: None,
+        }
+    }
+}
+
+// [count] (operator [count] motion | operator operator | motion | action)
+fn parse(keys: &[char]) -> Parse<Command> {
+    let (count, rest) = take_count(keys);
+    let (c, rest) = match rest.split_first() {
+        Some(split) => split,
+        None => return Parse::Pending,
+    };
+
+    let operator = match c {
+        'd' => Some(Operator::Delete),
+        'c' => Some(Operator::Change),
+        'y' => Some(Operator::Yank),
+        _ => None,
+    };
+    if let Some(operator) = operator {
+        let (count2, rest) = take_count(rest);
+        let count = match (count, count2) {
+            (None, None) => None,
+            (count, count2) => Some(count.unwrap_or(1) * count2.unwrap_or(1)),
+        };
+        return match rest {
+            [] => Parse::Pending,
+            [c2] if c2 == c => Parse::Done(Command::Operate(operator, count, Motion::Line)),
+            rest => match parse_motion(rest) {
+                Parse::Done(motion) => Parse::Done(Command::Operate(operator, count, motion)),
+                Parse::Pending => Parse::Pending,
+                Parse::Invalid => Parse::Invalid,
+            },
+        };
+    }
+
+    match parse_motion(&keys[keys.len() - rest.len() - 1..]) {
+        Parse::Done(motion) => return Parse::Done(Command::Move(count, motion)),
+        Parse::Pending => return Parse::Pending,
+        Parse::Invalid => {}
+    }
+
+    let action = match c {
+        'i' => Action::Insert,
+        'a' => Action::Append,
+        'I' => Action::InsertLineStart,
+        'A' => Action::AppendLineEnd,
+        'o' => Action::OpenBelow,
+        'O' => Action::OpenAbove,
+        'x' => Action::DeleteChar,
+        'p' => Action::PasteAfter,
+        'P' => Action::PasteBefore,
+        'u' => Action::Undo,
+        'v' => Action::Visual,
+        '.' => Action::Repeat,
+        ':' => Action::CommandLine,
+        _ => return Parse::Invalid,
+    };
+    if rest.is_empty() {
+        Parse::Done(Command::Act(count, action))
+    } else {
+        Parse::Invalid
+    }
+}
+
+fn parse_motion(keys: &[char]) -> Parse<Motion> {
+    let motion = match keys {
+        ['h'] => Motion::Left,
+        ['l'] => Motion::Right,
+        ['k'] => Motion::Up,
+        ['j'] => Motion::Down,
+        ['w'] => Motion::WordForward,
+        ['b'] => Motion::WordBackward,
+        ['e'] => Motion::WordEnd,
+        ['0'] => Motion::LineStart,
+        ['$'] => Motion::LineEnd,
+        ['G'] => Motion::LastLine,
+        ['g', 'g'] => Motion::FirstLine,
+        ['f', c] => Motion::FindChar(*c),
+        ['%'] => Motion::MatchBracket,
+        ['}'] => Motion::ParagraphForward,
+        ['{'] => Motion::ParagraphBackward,
+        ['g'] | ['f'] => return Parse::Pending,
+        _ => return Parse::Invalid,
+    };
+
+    Parse::Done(motion)
+}
+
+// count doesn't start with 0, `0` is a motion
+fn take_count(keys: &[char]) -> (Option<usize>, &[char]) {
+    let n = keys
+        .iter()
+        .enumerate()
+        .take_while(|(i, c)| c.is_ascii_digit() && (0 < *i || **c != '0'))
+        .count();
+    if n == 0 {
+        return (None, keys);
+    }
+    let count = keys[..n].iter().collect::<String>().parse().ok();
+
+    (count, &keys[n..])
+}
+
+impl Editor {
+    pub fn set_vim(&mut self, vim: bool) {
+        self.vim = if vim { Some(Vim::default()) } else { None };
+    }
+
+    fn vim_mode(&self) -> Option<Mode> {
+        self.vim.as_ref().map(|vim| vim.mode)
+    }
+
+    pub(super) fn is_visual_mode(&self) -> bool {
+        self.vim_mode() == Some(Mode::Visual)
+    }
+
+    // handle the key in modal editing.
+    // return false if the key should be handled as modeless editing.
+    pub(super) fn vim_key(&mut self, key: &event::Key) -> error::Result<bool> {
+        let mode = match self.vim_mode() {
+            Some(mode) => mode,
+            None => return Ok(false),
+        };
+
+        if mode == Mode::Insert {
+            if *key == event::Key::Esc {
+                self.exit_insert_mode();
+                return Ok(true);
+            }
+            let recording = self.vim.as_mut().and_then(|vim| vim.recording.as_mut());
+            if let Some(change) = recording {
+                if matches!(key, event::Key::Char(_) | event::Key::Backspace) {
+                    change.keys.push(*key);
+                }
+            }
+            return Ok(false);
+        }
+
+        let c = match key {
+            event::Key::Char('\n') => 'j',
+            event::Key::Char(c) => *c,
+            event::Key::Backspace => 'h',
+            event::Key::Esc => {
+                self.clear_pending();
+                if mode == Mode::Visual {
+                    self.exit_visual_mode();
+                }
+                return Ok(true);
+            }
+            event::Key::Ctrl('r') => {
+                self.clear_pending();
+                self.redo()?;
+                return Ok(true);
+            }
+            // arrow keys and control keys work as modeless editing
+            _ => return Ok(false),
+        };
+
+        let no_pending = self.vim.as_ref().is_some_and(|vim| vim.pending.is_empty());
+        if mode == Mode::Visual && no_pending {
+            let operator = match c {
+                'd' | 'x' => Some(Operator::Delete),
+                'c' => Some(Operator::Change),
+                'y' => Some(Operator::Yank),
+                _ => None,
+            };
+            if let Some(operator) = operator {
+                self.operate_selection(operator)?;
+                return Ok(true);
+            }
+        }
+
+        let vim = match self.vim.as_mut() {
+            Some(vim) => vim,
+            None => return Ok(false),
+        };
+        vim.pending.push(c);
+        match parse(&vim.pending) {
+            Parse::Pending => {}
+            Parse::Invalid => vim.pending.clear(),
+            Parse::Done(command) => {
+                vim.pending.clear();
+                self.vim_execute(command)?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn clear_pending(&mut self) {
+        if let Some(vim) = self.vim.as_mut() {
+            vim.pending.clear();
+        }
+    }
+
+    fn vim_execute(&mut self, command: Command) -> error::Result<()> {
+        let visual = self.is_visual_mode();
+        let res = match command {
+            Command::Move(count, motion) => {
+                if let Some(pos) = self.motion_target(motion, count) {
+                    (self.cursor_y, self.cursor_x) = pos;
+                }
+                Ok(())
+            }
+            // operators in visual mode are handled by operate_selection
+            Command::Operate(..) if visual => Ok(()),
+            Command::Operate(operator, count, motion) => {
+                // yank doesn't edit, `.` repeats the change before it
+                if operator != Operator::Yank {
+                    self.record_change(command, operator == Operator::Change);
+                }
+                self.operate_motion(operator, count, motion)
+            }
+            Command::Act(_, Action::Visual) => {
+                if visual {
+                    self.exit_visual_mode();
+                } else {
+                    self.enter_visual_mode();
+                }
+                Ok(())
+            }
+            Command::Act(_, Action::CommandLine) => self.vim_command_line(),
+            Command::Act(..) if visual => Ok(()),
+            Command::Act(count, action) => {
+                if action.is_change() {
+                    self.record_change(command, action.enters_insert_mode());
+                }
+                self.act(count, action)
+            }
+        };
+        self.dirty = self.content.is_modified();
+
+        res
+    }
+
+    // save the command for `.`.
+    // commands entering insert mode are saved with typed text when insert mode ends.
+    fn record_change(&mut self, command: Command, insert: bool) {
+        if let Some(vim) = self.vim.as_mut() {
+            let change = Change {
+                command,
+                keys: Vec::new(),
+            };
+            if insert {
+                vim.recording = Some(change);
+            } else {
+                vim.last_change = Some(change);
+            }
+        }
+    }
+
+    fn enter_insert_mode(&mut self) {
+        if let Some(vim) = self.vim.as_mut() {
+            vim.mode = Mode::Insert;
+            // edits until Esc are undone at once
+            self.content.begin_undo_group();
+            self.set_status_message("-- INSERT --");
+        }
+    }
+
+    pub(super) fn exit_insert_mode(&mut self) {
+        let vim = match self.vim.as_mut() {
+            Some(vim) if vim.mode == Mode::Insert => vim,
+            _ => return,
+        };
+        vim.mode = Mode::Normal;
+        if let Some(change) = vim.recording.take() {
+            vim.last_change = Some(change);
+        }
+        self.content.end_undo_group();
+        self.set_status_message("");
+
+        // cursor is on the last inserted char like vi
+        let cursor_x = self.cursor_x;
+        if let Some(row) = self.current_row() {
+            let col = row.prev_boundary(cursor_x);
+            self.cursor_x = col;
+        }
+    }
+
+    fn enter_visual_mode(&mut self) {
+        if let Some(vim) = self.vim.as_mut() {
+            vim.mode = Mode::Visual;
+            self.mark = Some(Mark {
+                row: self.cursor_y,
+                col: self.cursor_x,
+                shift: false,
+            });
+            self.set_status_message("-- VISUAL --");
+        }
+    }
+
+    fn exit_visual_mode(&mut self) {
+        if let Some(vim) = self.vim.as_mut() {
+            vim.mode = Mode::Normal;
+            self.mark = None;
+            self.set_status_message("");
+        }
+    }
+
+    // return the position moved by the motion count times, or None if the motion fails
+    fn motion_target(&mut self, motion: Motion, count: Option<usize>) -> Option<(usize, usize)> {
+        let n = count.unwrap_or(1);
+        let mut pos = (self.cursor_y, self.cursor_x);
+        let last_row = self.num_rows().saturating_sub(1);
+
+        match motion {
+            Motion::Left | Motion::Right => {
+                let row = self.content.row_from_index(pos.0)?;
+                for _ in 0..n {
+                    pos.1 = if motion == Motion::Left {
+                        row.prev_boundary(pos.1)
+                    } else {
+                        row.next_boundary(pos.1)
+                    };
+                }
+            }
+            Motion::Up | Motion::Down => {
+                let key = if motion == Motion::Up {
+                    event::Key::Up
+                } else {
+                    event::Key::Down
+                };
+                // keep the column on the screen
+                let saved = (self.cursor_y, self.cursor_x);
+                for _ in 0..n {
+                    self.update_cursor_state(&key);
+                }
+                pos = (self.cursor_y, self.cursor_x);
+                (self.cursor_y, self.cursor_x) = saved;
+            }
+            Motion::WordForward | Motion::WordBackward | Motion::WordEnd => {
+                let mut scanner = Scanner::new(&self.content, self.word_chars());
+                for _ in 0..n {
+                    pos = match motion {
+                        Motion::WordForward => scanner.word_forward(pos),
+                        Motion::WordBackward => scanner.word_backward(pos),
+                        _ => scanner.word_end(pos),
+                    };
+                }
+            }
+            Motion::LineStart => pos.1 = 0,
+            Motion::LineEnd => pos = Scanner::new(&self.content, self.word_chars()).line_end(pos.0),
+            Motion::FirstLine | Motion::LastLine => {
+                let row = match count {
+                    Some(line) => line.saturating_sub(1).min(last_row),
+                    None if motion == Motion::FirstLine => 0,
+                    None => last_row,
+                };
+                pos = (row, 0);
+            }
+            Motion::FindChar(c) => {
+                let mut scanner = Scanner::new(&self.content, self.word_chars());
+                for _ in 0..n {
+                    pos = scanner.find_char(pos, c)?;
+                }
+            }
+            Motion::MatchBracket => pos = self.content.matching_bracket(pos)?,
+            Motion::ParagraphForward | Motion::ParagraphBackward => {
+                let mut scanner = Scanner::new(&self.content, self.word_chars());
+                for _ in 0..n {
+                    pos = if motion == Motion::ParagraphForward {
+                        scanner.paragraph_forward(pos)
+                    } else {
+                        scanner.paragraph_backward(pos)
+                    };
+                }
+            }
+            Motion::Line => pos = ((pos.0 + n - 1).min(last_row), 0),
+        }
+
+        Some(pos)
+    }
+
+    fn operate_motion(
+        &mut self,
+        operator: Operator,
+        count: Option<usize>,
+        motion: Motion,
+    ) -> error::Result<()> {
+        let cursor = (self.cursor_y, self.cursor_x);
+        let target = match self.motion_target(motion, count) {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+        let (start, end) = if target < cursor {
+            (target, cursor)
+        } else {
+            (cursor, target)
+        };
+
+        if motion.is_linewise() {
+            return self.operate_lines(operator, start.0, end.0);
+        }
+
+        let mut scanner = Scanner::new(&self.content, self.word_chars());
+        let mut end = if motion.is_inclusive() {
+            scanner.char_end(end)
+        } else if motion == Motion::WordForward && start.0 < end.0 {
+            // dw doesn't delete the line break before the next word
+            scanner.line_end(start.0)
+        } else {
+            end
+        };
+        // cw on a word doesn't change blanks after the word like ce
+        if operator == Operator::Change && motion == Motion::WordForward {
+            let line = self.content.line(start.0).unwrap_or_default();
+            let on_word = line[start.1..].starts_with(|c: char| !c.is_whitespace());
+            if on_word {
+                end.1 = start.1 + line[start.1..end.1].trim_end().len();
+            }
+        }
+
+        self.operate(operator, start, end)
+    }
+
+    fn operate_selection(&mut self, operator: Operator) -> error::Result<()> {
+        let selection = self.selection();
+        self.exit_visual_mode();
+        match selection {
+            Some((start, end)) => self.operate(operator, start, end),
+            None => Ok(()),
+        }
+    }
+
+    // operate on text between positions
+    fn operate(
+        &mut self,
+        operator: Operator,
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> error::Result<()> {
+        let text = self.content.text_range(start, end);
+        if !text.is_empty() {
+            self.kill(text);
+        }
+        if operator == Operator::Change {
+            self.enter_insert_mode();
+        }
+        if operator != Operator::Yank {
+            self.content.delete_range(start, end)?;
+        }
+        (self.cursor_y, self.cursor_x) = start;
+
+        Ok(())
+    }
+
+    // operate on rows first..=last
+    fn operate_lines(
+        &mut self,
+        operator: Operator,
+        first: usize,
+        last: usize,
+    ) -> error::Result<()> {
+        let num_rows = self.num_rows();
+        if num_rows == 0 {
+            return Ok(());
+        }
+        let mut scanner = Scanner::new(&self.content, self.word_chars());
+        let last_end = scanner.line_end(last);
+        let prev_end = scanner.line_end(first.saturating_sub(1));
+        // linewise text ends with `\n`
+        let text = format!("{}\n", self.content.text_range((first, 0), last_end));
+        self.kill(text);
+
+        match operator {
+            Operator::Yank => {}
+            Operator::Change => {
+                self.enter_insert_mode();
+                self.content.delete_range((first, 0), last_end)?;
+            }
+            Operator::Delete if last + 1 < num_rows => {
+                self.content.delete_range((first, 0), (last + 1, 0))?;
+            }
+            Operator::Delete if 0 < first => self.content.delete_range(prev_end, last_end)?,
+            // all rows are deleted, an empty row is left
+            Operator::Delete => self.content.delete_range((0, 0), last_end)?,
+        }
+        self.cursor_y = first.min(self.num_rows().saturating_sub(1));
+        self.cursor_x = 0;
+
+        Ok(())
+    }
+
+    fn act(&mut self, count: Option<usize>, action: Action) -> error::Result<()> {
+        let cursor = (self.cursor_y, self.cursor_x);
+        let mut scanner = Scanner::new(&self.content, self.word_chars());
+
+        match action {
+            Action::Insert => self.enter_insert_mode(),
+            Action::Append => {
+                self.cursor_x = scanner.char_end(cursor).1;
+                self.enter_insert_mode();
+            }
+            Action::InsertLineStart => {
+                let line = self.content.line(cursor.0).unwrap_or_default();
+                self.cursor_x = line.len() - line.trim_start().len();
+                self.enter_insert_mode();
+            }
+            Action::AppendLineEnd => {
+                self.cursor_x = scanner.line_end(cursor.0).1;
+                self.enter_insert_mode();
+            }
+            Action::OpenBelow => {
+                (self.cursor_y, self.cursor_x) = scanner.line_end(cursor.0);
+                self.enter_insert_mode();
+                self.insert_new_line()?;
+            }
+            Action::OpenAbove => {
+                self.enter_insert_mode();
+                // the new line keeps the indent of the current line
+                let line = self.content.line(cursor.0).unwrap_or_default();
+                let indent = if self.config.settings.auto_indent {
+                    &line[..line.len() - line.trim_start().len()]
+                } else {
+                    ""
+                };
+                self.content.insert_new_line(cursor.0, 0)?;
+                self.content.replace(cursor.0, 0, 0, indent)?;
+                self.cursor_x = indent.len();
+            }
+            Action::DeleteChar => {
+                let mut end = cursor;
+                for _ in 0..count.unwrap_or(1) {
+                    end = scanner.char_end(end);
+                }
+                self.operate(Operator::Delete, cursor, end)?;
+            }
+            Action::PasteAfter | Action::PasteBefore => {
+                self.vim_paste(count.unwrap_or(1), action == Action::PasteAfter)?;
+            }
+            Action::Undo => self.undo()?,
+            Action::Repeat => self.repeat_change(count)?,
+            Action::Visual | Action::CommandLine => {}
+        }
+
+        Ok(())
+    }
+
+    fn vim_paste(&mut self, count: usize, after: bool) -> error::Result<()> {
+        let text = match self.kill_ring.last() {
+            Some(text) => text.repeat(count),
+            None => return Ok(()),
+        };
+        let cursor = (self.cursor_y, self.cursor_x);
+        let mut scanner = Scanner::new(&self.content, self.word_chars());
+        let end = scanner.line_end(cursor.0);
+        let char_end = scanner.char_end(cursor);
+
+        self.content.begin_undo_group();
+        let res = match text.strip_suffix('\n') {
+            // linewise text is pasted below or above the row
+            Some(lines) if after => self
+                .content
+                .insert_text(end.0, end.1, &format!("\n{}", lines))
+                .map(|_| (cursor.0 + 1, 0)),
+            Some(_) => self
+                .content
+                .insert_text(cursor.0, 0, &text)
+                .map(|_| (cursor.0, 0)),
+            None => {
+                let pos = if after { char_end } else { cursor };
+                self.content.insert_text(pos.0, pos.1, &text)
+            }
+        };
+        self.content.end_undo_group();
+
+        (self.cursor_y, self.cursor_x) = res?;
+        Ok(())
+    }
+
+    // repeat the last change, with the count replaced if given.
+    // typed keys are replayed as typed, with auto-indent and expand_tabs.
+    fn repeat_change(&mut self, count: Option<usize>) -> error::Result<()> {
+        let mut change = match self.vim.as_ref().and_then(|vim| vim.last_change.clone()) {
+            Some(change) => change,
+            None => return Ok(()),
+        };
+        // `3.` of `ofoo<Esc>` opens 3 lines, `3.` of `ifoo<Esc>` inserts foofoofoo
+        let (mut executions, mut replays) = (1, 1);
+        if let Some(count) = count {
+            change.command = change.command.with_count(count);
+            match change.command {
+                Command::Act(_, Action::OpenBelow | Action::OpenAbove) => executions = count,
+                Command::Act(_, action) if action.enters_insert_mode() => replays = count,
+                _ => {}
+            }
+        }
+
+        let res = (0..executions).try_for_each(|_| {
+            self.vim_execute(change.command)?;
+            if self.vim_mode() != Some(Mode::Insert) {
+                return Ok(());
+            }
+            let res = (0..replays)
+                .try_for_each(|_| change.keys.iter().try_for_each(|key| self.replay_key(key)));
+            self.exit_insert_mode();
+            res
+        });
+        if let Some(vim) = self.vim.as_mut() {
+            vim.last_change = Some(change);
+        }
+
+        res
+    }
+
+    fn replay_key(&mut self, key: &event::Key) -> error::Result<()> {
+        match key {
+            event::Key::Char('\n') => self.insert_new_line(),
+            event::Key::Char(c) => self.insert_char(*c),
+            event::Key::Backspace => self.backspace_char(),
+            _ => Ok(()),
+        }
+    }
+
+    // :w, :q, :q!, :wq, :x, :e <file>, :set <option>, :<line number>,
+    // :buffers (:ls) to pick a buffer and :b <buffer number>
+    fn vim_command_line(&mut self) -> error::Result<()> {
+        let input = match self.prompt(":", None) {
+            Some(input) => input,
+            None => return Ok(()),
+        };
+
+        match input.trim() {
+            "w" => self.save()?,
+            "q" => {
+                let dirty_buffers = self.dirty_buffers();
+                if !dirty_buffers.is_empty() {
+                    let msg = format!(
+                        "{} has unsaved changes (add ! to override)",
+                        dirty_buffers.join(", ")
+                    );
+                    return Err(Error::Command(msg));
+                }
+                self.quit = true;
+            }
+            "q!" => self.quit = true,
+            "buffers" | "ls" => self.pick_buffer(),
+            "wq" | "x" => {
+                self.save()?;
+                // save may be aborted
+                self.quit = !self.dirty;
+            }
+            cmd => {
+                if let Some(path) = cmd.strip_prefix("e ") {
+                    self.open(path.trim().to_string())?;
+                } else if cmd == "set" || cmd.starts_with("set ") {
+                    self.set_option(&cmd[3..])?;
+                } else if let Some(n) = cmd
+                    .strip_prefix('b')
+                    .and_then(|n| n.trim().parse::<usize>().ok())
+                {
+                    if n == 0 || self.buffers.len() < n {
+                        return Err(Error::Command(format!("no buffer: {}", n)));
+                    }
+                    self.select_buffer(n - 1);
+                } else if let Ok(line) = cmd.parse::<usize>() {
+                    self.cursor_y = line.saturating_sub(1).min(self.num_rows());
+                    self.cursor_x = 0;
+                } else {
+                    let msg = format!("not an editor command: {}", cmd);
+                    return Err(Error::Command(msg));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+
+use crate::encoding::LineEnding;
+use crate::settings::FileSettings;
+
+const FILENAME: &str = ".editorconfig";
+// numeric ranges like `{1..120}` larger than this are not expanded
+const MAX_RANGE: i64 = 1000;
+
+// settings of the file from `.editorconfig` files in its directory and the parents.
+// the search stops at a file with `root = true`.
+pub fn resolve(path: &Path) -> FileSettings {
+    let path = absolute(path);
+
+    // from the root to the nearest, so that nearer files override
+    let mut files: Vec<(PathBuf, String)> = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        if let Ok(text) = fs::read_to_string(dir.join(FILENAME)) {
+            let root = is_root(&text);
+            files.push((dir.to_path_buf(), text));
+            if root {
+                break;
+            }
+        }
+    }
+
+    let mut properties = HashMap::new();
+    for (dir, text) in files.iter().rev() {
+        apply(&mut properties, dir, text, &path);
+    }
+
+    file_settings(&properties)
+}
+
+// absolute path without `.` and `..`, so that only directories containing the file are searched.
+// symlinks of the directory are resolved if it exists.
+fn absolute(path: &Path) -> PathBuf {
+    let path = match env::current_dir() {
+        Ok(dir) => normalize(&dir.join(path)),
+        Err(_) => normalize(path),
+    };
+    let canonical = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => fs::canonicalize(dir).ok().map(|dir| dir.join(name)),
+        _ => None,
+    };
+
+    canonical.unwrap_or(path)
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+
+    normalized
+}
+
+fn is_root(text: &str) -> bool {
+    lines(text)
+        .take_while(|line| !line.starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .any(|(key, value)| key.trim().eq_ignore_ascii_case("root") && value.trim() == "true")
+}
+
+// lines without comments and blanks
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+}
+
+// set properties of sections matching the path, later sections win
+fn apply(properties: &mut HashMap<String, String>, dir: &Path, text: &str, path: &Path) {
+    let path = path.to_string_lossy();
+    let mut matched = false;
+    for line in lines(text) {
+        if let Some(glob) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            matched = section_regex(dir, glob).is_some_and(|regex| regex.is_match(&path));
+        } else if let Some((key, value)) = line.split_once('=') {
+            if matched {
+                properties.insert(key.trim().to_lowercase(), value.trim().to_lowercase());
+            }
+        }
+    }
+}
+
+// a glob without `/` matches file names in any subdirectory
+fn section_regex(dir: &Path, glob: &str) -> Option<Regex> {
+    let dir = regex::escape(&dir.to_string_lossy());
+    let dir = dir.trim_end_matches('/');
+    let pattern = match glob.strip_prefix('/') {
+        Some(glob) => format!("^{}/{}$", dir, glob_to_regex(glob)),
+        None if glob.contains('/') => format!("^{}/{}$", dir, glob_to_regex(glob)),
+        None => format!("^{}/(?:.*/)?{}$", dir, glob_to_regex(glob)),
+    };
+
+    Regex::new(&pattern).ok()
+}
+
+// `*`, `**`, `?`, `[abc]`, `[!abc]`, `{a,b}` and `{1..3}` of editorconfig
+fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut pattern = String::new();
+    let mut i = 0;
+    // whether each open brace is an alternation
+    let mut braces: Vec<bool> = Vec::new();
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                pattern.push_str(".*");
+                i += 1;
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '[' => match chars[i + 1..].iter().position(|c| *c == ']') {
+                Some(len) => {
+                    let class: String = chars[i + 1..i + 1 + len].iter().collect();
+                    let class = match class.strip_prefix('!') {
+                        Some(class) => format!("^{}", class),
+                        None => class,
+                    };
+                    pattern.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+                    i += len + 1;
+                }
+                None => pattern.push_str(r"\["),
+            },
+            '{' => {
+                let close = chars[i + 1..].iter().position(|c| *c == '}');
+                let inner: Option<String> =
+                    close.map(|len| chars[i + 1..i + 1 + len].iter().collect());
+                if let Some(range) = inner.as_deref().and_then(number_range) {
+                    pattern.push_str(&range);
+                    i += close.unwrap_or(0) + 1;
+                } else if inner.as_deref().is_some_and(|s| s.contains(',')) {
+                    pattern.push_str("(?:");
+                    braces.push(true);
+                } else {
+                    pattern.push_str(r"\{");
+                    braces.push(false);
+                }
+            }
+            '}' => match braces.pop() {
+                Some(true) => pattern.push(')'),
+                _ => pattern.push_str(r"\}"),
+            },
+            ',' if braces.last() == Some(&true) => pattern.push('|'),
+            '\\' if i + 1 < chars.len() => {
+                pattern.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 1;
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+
+    pattern
+}
+
+// `1..3` to `(?:1|2|3)`
+fn number_range(s: &str) -> Option<String> {
+    let (start, end) = s.split_once("..")?;
+    let start: i64 = start.parse().ok()?;
+    let end: i64 = end.parse().ok()?;
+    let (start, end) = (start.min(end), start.max(end));
+    if MAX_RANGE < end - start {
+        return Some(r"-?\d+".to_string());
+    }
+
+    let numbers: Vec<String> = (start..=end).map(|n| n.to_string()).collect();
+    Some(format!("(?:{})", numbers.join("|")))
+}
+
+fn file_settings(properties: &HashMap<String, String>) -> FileSettings {
+    let get = |key: &str| properties.get(key).map(String::as_str);
+    let number = |key: &str| get(key).and_then(|value| value.parse::<usize>().ok());
+    let boolean = |key: &str| match get(key) {
+        Some("true") => Some(true),
+        Some("false") => Some(false),
+        _ => None,
+    };
+
+    // tab_width defaults to indent_size
+    let tab_stop = number("tab_width")
+        .or(number("indent_size"))
+        .filter(|n| 0 < *n);
+    FileSettings {
+        tab_stop,
+        expand_tabs: match get("indent_style") {
+            Some("space") => Some(true),
+            Some("tab") => Some(false),
+            _ => None,
+        },
+        // `indent_size = tab` is the tab width
+        indent_size: match get("indent_size") {
+            Some("tab") => tab_stop,
+            _ => number("indent_size").filter(|n| 0 < *n),
+        },
+        end_of_line: match get("end_of_line") {
+            Some("lf") => Some(LineEnding::Lf),
+            Some("crlf") => Some(LineEnding::CrLf),
+            _ => None,
+        },
+        insert_final_newline: boolean("insert_final_newline"),
+        trim_trailing_whitespace: boolean("trim_trailing_whitespace"),
+        word_chars: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    // directory in the temporary directory, unique to the test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("kiroro-test-{}-{}", process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn matches(glob: &str, path: &str) -> bool {
+        Regex::new(&format!("^{}$", glob_to_regex(glob)))
+            .unwrap()
+            .is_match(path)
+    }
+
+    #[test]
+    fn globs() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "src/main.rs"));
+        assert!(matches("**.rs", "src/main.rs"));
+        assert!(matches("?.c", "a.c"));
+        assert!(!matches("?.c", "ab.c"));
+        assert!(matches("[ab].c", "b.c"));
+        assert!(!matches("[!ab].c", "b.c"));
+        assert!(matches("*.{js,ts}", "a.ts"));
+        assert!(!matches("*.{js,ts}", "a.rs"));
+        assert!(matches("a{1..3}", "a2"));
+        assert!(!matches("a{1..3}", "a4"));
+        // special chars of regex and unmatched braces are literal
+        assert!(matches("a+b(c).md", "a+b(c).md"));
+        assert!(matches("{a", "{a"));
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+    }
+
+    #[test]
+    fn number_ranges() {
+        assert_eq!(number_range("1..3").as_deref(), Some("(?:1|2|3)"));
+        assert_eq!(number_range("2..-1").as_deref(), Some("(?:-1|0|1|2)"));
+        assert_eq!(number_range("0..100000").as_deref(), Some(r"-?\d+"));
+        assert_eq!(number_range("a..3"), None);
+        assert_eq!(number_range("1,3"), None);
+    }
+
+    #[test]
+    fn resolve_nearest_wins() {
+        let dir = temp_dir("editorconfig-nearest");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        let root = "root = true\n[*]\nindent_style = space\nindent_size = 4\n\
+                    [*.md]\ntrim_trailing_whitespace = false\n";
+        fs::write(dir.join(".editorconfig"), root).unwrap();
+        let nearest = "[*.rs]\nindent_size = 2\nend_of_line = crlf\n[/src/lib.rs]\ntab_width = 8\n";
+        fs::write(dir.join("src/.editorconfig"), nearest).unwrap();
+
+        let main = resolve(&dir.join("src/main.rs"));
+        let lib = resolve(&dir.join("src/lib.rs"));
+        let md = resolve(&dir.join("README.md"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(main.expand_tabs, Some(true));
+        assert_eq!(main.indent_size, Some(2));
+        assert_eq!(main.tab_stop, Some(2));
+        assert_eq!(main.end_of_line, Some(LineEnding::CrLf));
+        // `/` is relative to the directory of the file
+        assert_eq!(lib.tab_stop, Some(2));
+        assert_eq!(md.tab_stop, Some(4));
+        assert_eq!(md.trim_trailing_whitespace, Some(false));
+    }
+
+    #[test]
+    fn resolve_parent_dir() {
+        let dir = temp_dir("editorconfig-parent");
+        fs::create_dir_all(dir.join("project/src")).unwrap();
+        fs::create_dir_all(dir.join("other")).unwrap();
+        fs::write(
+            dir.join("project/.editorconfig"),
+            "root = true\n[*]\nindent_size = 2\n",
+        )
+        .unwrap();
+        fs::write(dir.join("other/.editorconfig"), "[*]\nindent_style = tab\n").unwrap();
+
+        // the path goes through project, but the file is in other
+        let settings = resolve(&dir.join("project/src/../../other/a.rs"));
+        let project = resolve(&dir.join("project/src/./a.rs"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(settings.expand_tabs, Some(false));
+        assert_eq!(settings.indent_size, None);
+        assert_eq!(project.indent_size, Some(2));
+    }
+}
@@ -0,0 +1,147 @@
+use std::io::{self, Write};
+
+use crate::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::CrLf => "CRLF",
+        }
+    }
+
+    // detect by the first line break
+    pub fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(i) if text[..i].ends_with('\r') => Self::CrLf,
+            _ => Self::Lf,
+        }
+    }
+
+    // whether both LF and CRLF are used.
+    // such text is converted to the detected line ending on write.
+    pub fn is_mixed(text: &str) -> bool {
+        let mut breaks = text
+            .split_inclusive('\n')
+            .filter(|line| line.ends_with('\n'));
+        match breaks.next() {
+            Some(first) => {
+                let crlf = first.ends_with("\r\n");
+                breaks.any(|line| line.ends_with("\r\n") != crlf)
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    // fallback for files which are not valid UTF-8.
+    // each byte is mapped to a char of the same code point, so any bytes round-trip.
+    Latin1,
+}
+
+const UTF8_BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+const UTF16LE_BOM: &[u8] = &[0xff, 0xfe];
+const UTF16BE_BOM: &[u8] = &[0xfe, 0xff];
+
+impl Encoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+            Self::Latin1 => "latin-1",
+        }
+    }
+
+    // return (text, encoding, has bom)
+    pub fn decode(bytes: &[u8]) -> (String, Self, bool) {
+        if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+            if let Ok(text) = std::str::from_utf8(rest) {
+                return (text.to_string(), Self::Utf8, true);
+            }
+        }
+        if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+            if let Some(text) = decode_utf16(rest, u16::from_le_bytes) {
+                return (text, Self::Utf16Le, true);
+            }
+        }
+        if let Some(rest) = bytes.strip_prefix(UTF16BE_BOM) {
+            if let Some(text) = decode_utf16(rest, u16::from_be_bytes) {
+                return (text, Self::Utf16Be, true);
+            }
+        }
+
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_string(), Self::Utf8, false),
+            Err(_) => (
+                bytes.iter().map(|b| *b as char).collect(),
+                Self::Latin1,
+                false,
+            ),
+        }
+    }
+
+    pub fn bom(&self) -> &'static [u8] {
+        match self {
+            Self::Utf8 => UTF8_BOM,
+            Self::Utf16Le => UTF16LE_BOM,
+            Self::Utf16Be => UTF16BE_BOM,
+            Self::Latin1 => &[],
+        }
+    }
+
+    // write encoded text, return the number of written bytes
+    pub fn encode(&self, text: &str, out: &mut dyn Write) -> io::Result<usize> {
+        let bytes: Vec<u8> = match self {
+            Self::Utf8 => {
+                out.write_all(text.as_bytes())?;
+                return Ok(text.len());
+            }
+            Self::Utf16Le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Self::Utf16Be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            Self::Latin1 => text
+                .chars()
+                .map(|c| {
+                    u8::try_from(c).map_err(|_| {
+                        let msg = format!("{:?} can't be encoded in {}", c, self.name());
+                        io::Error::new(io::ErrorKind::InvalidData, Error::Encoding(msg))
+                    })
+                })
+                .collect::<io::Result<_>>()?,
+        };
+        out.write_all(&bytes)?;
+
+        Ok(bytes.len())
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bytes.chunks_exact(2).map(|b| from_bytes([b[0], b[1]]));
+
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .ok()
+}
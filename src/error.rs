@@ -0,0 +1,44 @@
+use std::{error, fmt, io};
+
+// errors of the editor, shown in the status bar
+#[derive(Debug)]
+pub enum Error {
+    // reading or writing files and the terminal
+    Io(io::Error),
+    // text can't be encoded in the encoding of the file
+    Encoding(String),
+    // position out of the buffer
+    Edit(String),
+    // invalid settings or keymap
+    Config(String),
+    // invalid command of the user
+    Command(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Encoding(msg) | Self::Edit(msg) | Self::Config(msg) | Self::Command(msg) => {
+                write!(f, "{}", msg)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
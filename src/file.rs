@@ -0,0 +1,104 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::os::unix::fs::{chown, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::process;
+
+// `$XDG_CONFIG_HOME/kiroro`, or `~/.config/kiroro` if it is not set
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join("kiroro"))
+}
+
+// write a file without leaving it truncated on crash.
+// data is written into a temporary file in the same directory and
+// the temporary file is renamed over the path after fsync.
+// if backup is true, the original file is copied to `path~` before renaming.
+// return the number of bytes written by `write`.
+pub fn write_atomic<F>(path: &Path, backup: bool, write: F) -> io::Result<usize>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<usize>,
+{
+    // write through symlink, not replace it
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => path.to_path_buf(),
+        Err(e) => return Err(e),
+    };
+    let original = match fs::metadata(&path) {
+        Ok(metadata) => Some(metadata),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    let tmp_path = temporary_path(&path);
+    let res = write_temporary(&tmp_path, original.as_ref(), write).and_then(|written| {
+        if backup && original.is_some() {
+            fs::copy(&path, append_to_path(&path, "~"))?;
+        }
+        fs::rename(&tmp_path, &path)?;
+        Ok(written)
+    });
+
+    match res {
+        Ok(written) => {
+            // persist the rename. some file systems don't support fsync on directory.
+            if let Some(dir) = path.parent().and_then(|dir| File::open(dir).ok()) {
+                let _ = dir.sync_all();
+            }
+            Ok(written)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+fn write_temporary<F>(
+    tmp_path: &Path,
+    original: Option<&fs::Metadata>,
+    write: F,
+) -> io::Result<usize>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<usize>,
+{
+    let f = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(tmp_path)?;
+
+    if let Some(metadata) = original {
+        f.set_permissions(metadata.permissions())?;
+        // best effort: non-root user can't give a file to the other user
+        let _ = chown(tmp_path, Some(metadata.uid()), Some(metadata.gid()));
+    }
+
+    let mut out = BufWriter::new(f);
+    let written = write(&mut out)?;
+    let f = out.into_inner().map_err(|e| e.into_error())?;
+    f.sync_all()?;
+
+    Ok(written)
+}
+
+// return `.{filename}.{pid}.tmp` in the same directory of path
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", process::id()));
+
+    path.with_file_name(name)
+}
+
+fn append_to_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(suffix);
+
+    path.into()
+}
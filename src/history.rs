@@ -0,0 +1,237 @@
+// row and col in an operation are the indices passed to Content,
+// so applying `op.inverse()` restores the content before `op`.
+#[derive(Debug, Clone)]
+pub enum Operation {
+    Insert {
+        row: usize,
+        col: usize,
+        text: String,
+    },
+    // `cursor` is the position of the cursor before deletion,
+    // after the text by backspace or at `col` by delete key
+    Delete {
+        row: usize,
+        col: usize,
+        text: String,
+        cursor: (usize, usize),
+    },
+    // split `row` at `col`, second half becomes `row + 1`
+    SplitLine {
+        row: usize,
+        col: usize,
+    },
+    // concatenate `row` to `row - 1`, `col` is length of `row - 1` before joining
+    JoinLine {
+        row: usize,
+        col: usize,
+        cursor: (usize, usize),
+    },
+    // insert an empty row at `row`
+    InsertLine {
+        row: usize,
+    },
+    RemoveLine {
+        row: usize,
+    },
+}
+
+impl Operation {
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::Insert { row, col, text } => Self::Delete {
+                row: *row,
+                col: *col,
+                text: text.clone(),
+                cursor: (*row, col + text.len()),
+            },
+            Self::Delete { row, col, text, .. } => Self::Insert {
+                row: *row,
+                col: *col,
+                text: text.clone(),
+            },
+            Self::SplitLine { row, col } => Self::JoinLine {
+                row: row + 1,
+                col: *col,
+                cursor: (row + 1, 0),
+            },
+            Self::JoinLine { row, col, .. } => Self::SplitLine {
+                row: row - 1,
+                col: *col,
+            },
+            Self::InsertLine { row } => Self::RemoveLine { row: *row },
+            Self::RemoveLine { row } => Self::InsertLine { row: *row },
+        }
+    }
+
+    // return (row idx, col idx) of the cursor before the operation is applied
+    pub fn cursor_before(&self) -> (usize, usize) {
+        match self {
+            Self::Insert { row, col, .. } | Self::SplitLine { row, col } => (*row, *col),
+            Self::Delete { cursor, .. } | Self::JoinLine { cursor, .. } => *cursor,
+            Self::InsertLine { row } => (*row, 0),
+            Self::RemoveLine { row } => (row + 1, 0),
+        }
+    }
+
+    // return (row idx, col idx) of the cursor after the operation is applied
+    pub fn cursor_after(&self) -> (usize, usize) {
+        match self {
+            Self::Insert { row, col, text } => (*row, col + text.len()),
+            Self::Delete { row, col, .. } => (*row, *col),
+            Self::SplitLine { row, .. } => (row + 1, 0),
+            Self::JoinLine { row, col, .. } => (row - 1, *col),
+            Self::InsertLine { row } => (row + 1, 0),
+            Self::RemoveLine { row } => (*row, 0),
+        }
+    }
+
+    // merge `next` into self when it continues the same typing or deletion.
+    // return false if they can't be merged.
+    fn merge(&mut self, next: &Self) -> bool {
+        match (self, next) {
+            (
+                Self::Insert { row, col, text },
+                Self::Insert {
+                    row: next_row,
+                    col: next_col,
+                    text: next_text,
+                },
+            ) if row == next_row && *col + text.len() == *next_col => {
+                text.push_str(next_text);
+                true
+            }
+            // cursor before the first deletion is kept
+            (
+                Self::Delete { row, col, text, .. },
+                Self::Delete {
+                    row: next_row,
+                    col: next_col,
+                    text: next_text,
+                    ..
+                },
+            ) if row == next_row => {
+                if *next_col + next_text.len() == *col {
+                    // backspace
+                    text.insert_str(0, next_text);
+                    *col = *next_col;
+                    true
+                } else if next_col == col {
+                    // delete key
+                    text.push_str(next_text);
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+// undo/redo history of Content.
+// each undo step is a group of operations which are undone together.
+pub struct History {
+    undo_stack: Vec<Vec<Operation>>,
+    redo_stack: Vec<Vec<Operation>>,
+    // when sealed, next operation starts a new group
+    sealed: bool,
+    // nesting depth of begin_group/end_group
+    group_depth: usize,
+    // true after the first operation inside begin_group/end_group is recorded
+    group_open: bool,
+    // length of undo_stack when content was saved.
+    // None when saved state is unreachable by undo/redo.
+    saved: Option<usize>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            sealed: false,
+            group_depth: 0,
+            group_open: false,
+            saved: Some(0),
+        }
+    }
+}
+
+impl History {
+    pub fn record(&mut self, op: Operation) {
+        if !self.redo_stack.is_empty() {
+            self.redo_stack.clear();
+            if self
+                .saved
+                .is_some_and(|saved| saved > self.undo_stack.len())
+            {
+                self.saved = None;
+            }
+        }
+
+        if let Some(group) = self.undo_stack.last_mut() {
+            if !self.sealed && group.last_mut().is_some_and(|last| last.merge(&op)) {
+                self.group_open = 0 < self.group_depth;
+                return;
+            }
+            if self.group_open {
+                group.push(op);
+                return;
+            }
+        }
+
+        self.undo_stack.push(vec![op]);
+        self.sealed = false;
+        self.group_open = 0 < self.group_depth;
+    }
+
+    // following operations are recorded into one group until end_group is called
+    pub fn begin_group(&mut self) {
+        if self.group_depth == 0 {
+            self.group_open = false;
+        }
+        self.group_depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+        if self.group_depth == 0 {
+            self.group_open = false;
+        }
+    }
+
+    // stop merging operations into the current group
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    // return operations to be undone, in the order they were applied
+    pub fn undo(&mut self) -> Option<Vec<Operation>> {
+        let group = self.undo_stack.pop()?;
+        self.redo_stack.push(group.clone());
+        self.sealed = true;
+        // following operations are not appended to the previous group
+        self.group_open = false;
+
+        Some(group)
+    }
+
+    // return operations to be redone, in the order they were applied
+    pub fn redo(&mut self) -> Option<Vec<Operation>> {
+        let group = self.redo_stack.pop()?;
+        self.undo_stack.push(group.clone());
+        self.sealed = true;
+        self.group_open = false;
+
+        Some(group)
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.undo_stack.len());
+        self.sealed = true;
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.saved != Some(self.undo_stack.len())
+    }
+}
@@ -0,0 +1,44 @@
+use termion::event::Key;
+
+// modifiers of special keys like shift+arrow.
+// termion parses only unmodified special keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+// parse xterm modified key sequence like `ESC [1;2A` (shift+up) or `ESC [3;5~` (ctrl+delete),
+// or `ESC [127;5u` (ctrl+backspace) of terminals reporting keys in CSI u
+pub fn parse_modified_key(seq: &[u8]) -> Option<(Key, Modifiers)> {
+    let seq = seq.strip_prefix(b"\x1b[")?;
+    let (last, params) = seq.split_last()?;
+    let params = std::str::from_utf8(params).ok()?;
+    let (code, modifier) = params.split_once(';')?;
+    // modifier is 1 + bitmask of shift(1), alt(2) and ctrl(4)
+    let bits = modifier.parse::<u8>().ok()?.checked_sub(1)?;
+    let modifiers = Modifiers {
+        shift: bits & 1 != 0,
+        alt: bits & 2 != 0,
+        ctrl: bits & 4 != 0,
+    };
+
+    let key = match (last, code) {
+        (b'A', "1") => Key::Up,
+        (b'B', "1") => Key::Down,
+        (b'C', "1") => Key::Right,
+        (b'D', "1") => Key::Left,
+        (b'H', "1") => Key::Home,
+        (b'F', "1") => Key::End,
+        (b'~', "1" | "7") => Key::Home,
+        (b'~', "3") => Key::Delete,
+        (b'~', "4" | "8") => Key::End,
+        (b'~', "5") => Key::PageUp,
+        (b'~', "6") => Key::PageDown,
+        (b'u', "127") => Key::Backspace,
+        _ => return None,
+    };
+
+    Some((key, modifiers))
+}
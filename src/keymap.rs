@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fs, io};
+
+use termion::event::Key;
+
+use crate::error::{self, Error};
+use crate::key::Modifiers;
+use crate::settings::strip_comment;
+
+// named editor commands which keys are bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Quit,
+    Save,
+    Find,
+    Replace,
+    ToggleLineEnding,
+    Open,
+    Set,
+    NextBuffer,
+    PreviousBuffer,
+    PickBuffer,
+    SplitHorizontal,
+    SplitVertical,
+    CloseWindow,
+    NextWindow,
+    GrowWindow,
+    ShrinkWindow,
+    Undo,
+    Redo,
+    SetMark,
+    Cut,
+    Copy,
+    Paste,
+    YankPop,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    MoveWordLeft,
+    MoveWordRight,
+    MoveParagraphUp,
+    MoveParagraphDown,
+    PageUp,
+    PageDown,
+    LineStart,
+    LineEnd,
+    FileStart,
+    FileEnd,
+    DeleteBackward,
+    DeleteForward,
+    DeleteWordBackward,
+    DeleteWordForward,
+    Newline,
+    Indent,
+    Outdent,
+    MatchBracket,
+}
+
+static COMMANDS: &[(&str, Command)] = &[
+    ("quit", Command::Quit),
+    ("save", Command::Save),
+    ("find", Command::Find),
+    ("replace", Command::Replace),
+    ("toggle-line-ending", Command::ToggleLineEnding),
+    ("open", Command::Open),
+    ("set", Command::Set),
+    ("next-buffer", Command::NextBuffer),
+    ("previous-buffer", Command::PreviousBuffer),
+    ("pick-buffer", Command::PickBuffer),
+    ("split-horizontal", Command::SplitHorizontal),
+    ("split-vertical", Command::SplitVertical),
+    ("close-window", Command::CloseWindow),
+    ("next-window", Command::NextWindow),
+    ("grow-window", Command::GrowWindow),
+    ("shrink-window", Command::ShrinkWindow),
+    ("undo", Command::Undo),
+    ("redo", Command::Redo),
+    ("set-mark", Command::SetMark),
+    ("cut", Command::Cut),
+    ("copy", Command::Copy),
+    ("paste", Command::Paste),
+    ("yank-pop", Command::YankPop),
+    ("move-up", Command::MoveUp),
+    ("move-down", Command::MoveDown),
+    ("move-left", Command::MoveLeft),
+    ("move-right", Command::MoveRight),
+    ("move-word-left", Command::MoveWordLeft),
+    ("move-word-right", Command::MoveWordRight),
+    ("move-paragraph-up", Command::MoveParagraphUp),
+    ("move-paragraph-down", Command::MoveParagraphDown),
+    ("page-up", Command::PageUp),
+    ("page-down", Command::PageDown),
+    ("line-start", Command::LineStart),
+    ("line-end", Command::LineEnd),
+    ("file-start", Command::FileStart),
+    ("file-end", Command::FileEnd),
+    ("delete-backward", Command::DeleteBackward),
+    ("delete-forward", Command::DeleteForward),
+    ("delete-word-backward", Command::DeleteWordBackward),
+    ("delete-word-forward", Command::DeleteWordForward),
+    ("newline", Command::Newline),
+    ("indent", Command::Indent),
+    ("outdent", Command::Outdent),
+    ("match-bracket", Command::MatchBracket),
+];
+
+static DEFAULT_BINDINGS: &[(&str, Command)] = &[
+    ("ctrl-q", Command::Quit),
+    ("ctrl-s", Command::Save),
+    ("ctrl-f", Command::Find),
+    ("ctrl-r", Command::Replace),
+    ("ctrl-e", Command::ToggleLineEnding),
+    ("ctrl-o", Command::Open),
+    ("alt-x", Command::Set),
+    ("ctrl-n", Command::NextBuffer),
+    ("ctrl-p", Command::PreviousBuffer),
+    ("ctrl-b", Command::PickBuffer),
+    ("alt-s", Command::SplitHorizontal),
+    ("alt-v", Command::SplitVertical),
+    ("alt-c", Command::CloseWindow),
+    ("alt-o", Command::NextWindow),
+    ("alt-+", Command::GrowWindow),
+    ("alt-=", Command::GrowWindow),
+    ("alt--", Command::ShrinkWindow),
+    ("ctrl-z", Command::Undo),
+    ("ctrl-y", Command::Redo),
+    ("ctrl-space", Command::SetMark),
+    ("ctrl-x", Command::Cut),
+    ("ctrl-c", Command::Copy),
+    ("ctrl-v", Command::Paste),
+    ("alt-y", Command::YankPop),
+    ("up", Command::MoveUp),
+    ("down", Command::MoveDown),
+    ("left", Command::MoveLeft),
+    ("right", Command::MoveRight),
+    ("ctrl-left", Command::MoveWordLeft),
+    ("ctrl-right", Command::MoveWordRight),
+    ("ctrl-up", Command::MoveParagraphUp),
+    ("ctrl-down", Command::MoveParagraphDown),
+    ("pageup", Command::PageUp),
+    ("pagedown", Command::PageDown),
+    ("home", Command::LineStart),
+    ("end", Command::LineEnd),
+    ("ctrl-home", Command::FileStart),
+    ("ctrl-end", Command::FileEnd),
+    ("backspace", Command::DeleteBackward),
+    ("ctrl-h", Command::DeleteBackward),
+    ("delete", Command::DeleteForward),
+    ("ctrl-backspace", Command::DeleteWordBackward),
+    ("ctrl-delete", Command::DeleteWordForward),
+    ("enter", Command::Newline),
+    ("alt-.", Command::Indent),
+    ("alt-,", Command::Outdent),
+    ("backtab", Command::Outdent),
+    ("alt-m", Command::MatchBracket),
+];
+
+impl Command {
+    pub fn from_name(name: &str) -> Option<Self> {
+        COMMANDS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, command)| *command)
+    }
+
+    pub fn name(&self) -> &'static str {
+        COMMANDS
+            .iter()
+            .find(|(_, command)| command == self)
+            .map_or("", |(name, _)| name)
+    }
+}
+
+// a key with ctrl and alt modifiers.
+// shift is not a part of bindings, shift+arrow extends selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    pub key: Key,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl KeyPress {
+    pub fn new(key: Key, modifiers: Modifiers) -> Self {
+        Self {
+            key,
+            ctrl: modifiers.ctrl,
+            alt: modifiers.alt,
+        }
+    }
+
+    // parse key name like `ctrl-s`, `alt-v`, `ctrl-left`, `enter` or `a`
+    pub fn parse(name: &str) -> Option<Self> {
+        let mut ctrl = false;
+        let mut alt = false;
+        let mut rest = name;
+        loop {
+            if let Some(r) = rest.strip_prefix("ctrl-") {
+                ctrl = true;
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("alt-") {
+                alt = true;
+                rest = r;
+            } else {
+                break;
+            }
+        }
+
+        let key = match rest {
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "home" => Key::Home,
+            "end" => Key::End,
+            "pageup" => Key::PageUp,
+            "pagedown" => Key::PageDown,
+            "backspace" => Key::Backspace,
+            "delete" => Key::Delete,
+            "insert" => Key::Insert,
+            "esc" => Key::Esc,
+            "enter" => Key::Char('\n'),
+            "tab" => Key::Char('\t'),
+            "backtab" => Key::BackTab,
+            "space" => Key::Char(' '),
+            _ => match rest.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => Key::F(n),
+                _ => {
+                    let mut chars = rest.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Key::Char(c),
+                        _ => return None,
+                    }
+                }
+            },
+        };
+
+        // modified chars are distinct keys in termion
+        let key = match key {
+            Key::Char(_) if ctrl && alt => return None,
+            Key::Char(' ') if ctrl => Key::Null,
+            Key::Char(c) if ctrl => Key::Ctrl(c.to_ascii_lowercase()),
+            Key::Char(c) if alt => Key::Alt(c),
+            key => {
+                return Some(Self { key, ctrl, alt });
+            }
+        };
+
+        Some(Self {
+            key,
+            ctrl: false,
+            alt: false,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        let key = match self.key {
+            Key::Ctrl(c) => format!("ctrl-{}", c),
+            Key::Alt(c) => format!("alt-{}", c),
+            Key::Null => "ctrl-space".to_string(),
+            Key::Char('\n') => "enter".to_string(),
+            Key::Char('\t') => "tab".to_string(),
+            Key::Char(' ') => "space".to_string(),
+            Key::Char(c) => c.to_string(),
+            Key::F(n) => format!("f{}", n),
+            Key::Up => "up".to_string(),
+            Key::Down => "down".to_string(),
+            Key::Left => "left".to_string(),
+            Key::Right => "right".to_string(),
+            Key::Home => "home".to_string(),
+            Key::End => "end".to_string(),
+            Key::PageUp => "pageup".to_string(),
+            Key::PageDown => "pagedown".to_string(),
+            Key::Backspace => "backspace".to_string(),
+            Key::Delete => "delete".to_string(),
+            Key::Insert => "insert".to_string(),
+            Key::Esc => "esc".to_string(),
+            Key::BackTab => "backtab".to_string(),
+            key => format!("{:?}", key),
+        };
+
+        let ctrl = if self.ctrl { "ctrl-" } else { "" };
+        let alt = if self.alt { "alt-" } else { "" };
+        format!("{}{}{}", ctrl, alt, key)
+    }
+}
+
+// like `ctrl-x ctrl-s`
+pub fn keys_name(keys: &[KeyPress]) -> String {
+    keys.iter()
+        .map(KeyPress::name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub enum Lookup {
+    Command(Command),
+    // keys are a prefix of bindings
+    Pending,
+    Unbound,
+}
+
+// key sequences to commands
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: HashMap<Vec<KeyPress>, Command>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            bindings: HashMap::new(),
+        };
+        for (name, command) in DEFAULT_BINDINGS {
+            if let Some(key) = KeyPress::parse(name) {
+                keymap.bind(vec![key], *command);
+            }
+        }
+
+        keymap
+    }
+}
+
+impl Keymap {
+    // override bindings by the file, missing file is not an error.
+    // valid lines are applied even if others are invalid.
+    pub fn load_file(&mut self, path: &Path) -> error::Result<()> {
+        match fs::read_to_string(path) {
+            Ok(text) => self.load(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(Error::Io(e)),
+        }
+    }
+
+    // a binding overrides bindings of its prefixes and of longer sequences starting with it
+    pub fn bind(&mut self, keys: Vec<KeyPress>, command: Command) {
+        self.unbind(&keys);
+        self.bindings.insert(keys, command);
+    }
+
+    pub fn unbind(&mut self, keys: &[KeyPress]) {
+        self.bindings
+            .retain(|bound, _| !bound.starts_with(keys) && !keys.starts_with(bound));
+    }
+
+    pub fn lookup(&self, keys: &[KeyPress]) -> Lookup {
+        if let Some(command) = self.bindings.get(keys) {
+            Lookup::Command(*command)
+        } else if self.bindings.keys().any(|bound| bound.starts_with(keys)) {
+            Lookup::Pending
+        } else {
+            Lookup::Unbound
+        }
+    }
+
+    // apply lines like `ctrl-x ctrl-s = save` or `ctrl-x = none`.
+    // `#` starts a comment as strip_comment says, so `alt-#` is a key.
+    pub fn load(&mut self, text: &str) -> error::Result<()> {
+        let mut errors = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Err(msg) = self.load_line(line) {
+                errors.push(format!("line {}: {}", i + 1, msg));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Config(errors.join(", ")))
+        }
+    }
+
+    fn load_line(&mut self, line: &str) -> Result<(), String> {
+        let (keys, command) = line
+            .rsplit_once('=')
+            .filter(|(keys, _)| !keys.trim().is_empty())
+            .ok_or_else(|| format!("expected `<keys> = <command>`, found `{}`", line))?;
+        let keys = keys
+            .split_whitespace()
+            .map(|name| KeyPress::parse(name).ok_or_else(|| format!("unknown key `{}`", name)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match command.trim() {
+            "none" => self.unbind(&keys),
+            name => {
+                let command = Command::from_name(name)
+                    .ok_or_else(|| format!("unknown command `{}`", name))?;
+                self.bind(keys, command);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,7 +1,22 @@
+pub mod content;
 pub mod editor;
+pub mod editorconfig;
+pub mod encoding;
+pub mod error;
+pub mod file;
+pub mod history;
+pub mod key;
+pub mod keymap;
+pub mod motion;
 pub mod row;
-pub mod content;
+pub mod screen;
+pub mod search;
+pub mod settings;
+pub mod signal;
+pub mod syntax;
+pub mod terminal;
+pub mod window;
 
 const VERSION: &str = "0.0.1";
-const TAB_STOP: usize = 4;
-const QUIT_TIMES: u8 = 2;
+// seconds
+const MESSAGE_TIMEOUT: u64 = 5;
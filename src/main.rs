@@ -3,13 +3,32 @@ use std::env;
 use kiroro::editor::Editor;
 use std::fs::File;
 use std::io::Read;
+use std::process;
 
 fn main() {
-    let path = env::args().nth(1);
-    // test_string(path.unwrap());
+    // `--backup` keeps `file~` on save
+    // `--osc52` copies to system clipboard through the terminal
+    // `--vim` enables modal editing
+    let (flags, paths): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    // test_string(paths[0].clone());
 
-    let mut editor = Editor::new();
-    editor.run(path);
+    let mut editor = match Editor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("kiroro: {}", e);
+            process::exit(1);
+        }
+    };
+    editor.set_backup(flags.iter().any(|flag| flag == "--backup"));
+    editor.set_osc52(flags.iter().any(|flag| flag == "--osc52"));
+    editor.set_vim(flags.iter().any(|flag| flag == "--vim"));
+    if let Err(e) = editor.run(paths) {
+        // restore the terminal before printing
+        drop(editor);
+        eprintln!("kiroro: {}", e);
+        process::exit(1);
+    }
 }
 
 #[allow(dead_code)]
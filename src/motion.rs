@@ -0,0 +1,262 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::content::Content;
+
+// cursor motions over content. positions are (row idx, col idx).
+// col idx equal to the row length is the end of the row, regarded as a blank.
+// motions step by grapheme clusters, which are classified by their base char.
+pub struct Scanner<'a> {
+    content: &'a Content,
+    // chars making words with alphanumerics, like `_`
+    word_chars: &'a str,
+    // the last row read from content
+    row: usize,
+    line: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+fn char_class(c: char, word_chars: &str) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Blank
+    } else if c.is_alphanumeric() || word_chars.contains(c) {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(content: &'a Content, word_chars: &'a str) -> Self {
+        Self {
+            content,
+            word_chars,
+            row: usize::MAX,
+            line: String::new(),
+        }
+    }
+
+    fn line(&mut self, row: usize) -> &str {
+        if row != self.row {
+            self.line = self.content.line(row).unwrap_or_default();
+            self.row = row;
+        }
+        &self.line
+    }
+
+    // base char of the grapheme cluster at pos
+    fn char_at(&mut self, (row, col): (usize, usize)) -> Option<char> {
+        self.line(row).get(col..).and_then(|s| s.chars().next())
+    }
+
+    fn grapheme_len(&mut self, (row, col): (usize, usize)) -> Option<usize> {
+        self.line(row)
+            .get(col..)
+            .and_then(|s| s.graphemes(true).next())
+            .map(str::len)
+    }
+
+    fn class_at(&mut self, pos: (usize, usize)) -> CharClass {
+        let word_chars = self.word_chars;
+        self.char_at(pos)
+            .map_or(CharClass::Blank, |c| char_class(c, word_chars))
+    }
+
+    fn is_empty_row(&mut self, row: usize) -> bool {
+        self.line(row).is_empty()
+    }
+
+    // a row of whitespaces separates paragraphs
+    fn is_blank_row(&mut self, row: usize) -> bool {
+        self.line(row).trim().is_empty()
+    }
+
+    // next grapheme position, the end of the row moves to the next row
+    fn next(&mut self, (row, col): (usize, usize)) -> Option<(usize, usize)> {
+        match self.grapheme_len((row, col)) {
+            Some(len) => Some((row, col + len)),
+            None if row + 1 < self.content.num_rows() => Some((row + 1, 0)),
+            None => None,
+        }
+    }
+
+    // previous grapheme position, the start of the row moves to the end of the previous row
+    fn prev(&mut self, (row, col): (usize, usize)) -> Option<(usize, usize)> {
+        if 0 < col {
+            let line = self.line(row);
+            let col = line[..col.min(line.len())]
+                .grapheme_indices(true)
+                .next_back()
+                .map_or(0, |(i, _)| i);
+            Some((row, col))
+        } else if 0 < row {
+            let len = self.line(row - 1).len();
+            Some((row - 1, len))
+        } else {
+            None
+        }
+    }
+
+    // start of the next word. an empty row is a word.
+    pub fn word_forward(&mut self, pos: (usize, usize)) -> (usize, usize) {
+        let mut p = pos;
+        let class = self.class_at(p);
+        // skip the rest of the word under cursor
+        if class != CharClass::Blank {
+            while self.class_at(p) == class {
+                match self.next(p) {
+                    Some(next) => p = next,
+                    None => return p,
+                }
+            }
+        }
+        while self.class_at(p) == CharClass::Blank {
+            if p != pos && p.1 == 0 && self.is_empty_row(p.0) {
+                return p;
+            }
+            match self.next(p) {
+                Some(next) => p = next,
+                None => return p,
+            }
+        }
+
+        p
+    }
+
+    // start of the word before cursor
+    pub fn word_backward(&mut self, pos: (usize, usize)) -> (usize, usize) {
+        let mut p = match self.prev(pos) {
+            Some(prev) => prev,
+            None => return pos,
+        };
+        while self.class_at(p) == CharClass::Blank {
+            if p.1 == 0 && self.is_empty_row(p.0) {
+                return p;
+            }
+            match self.prev(p) {
+                Some(prev) => p = prev,
+                None => return p,
+            }
+        }
+        let class = self.class_at(p);
+        while let Some(prev) = self.prev(p) {
+            if prev.0 != p.0 || self.class_at(prev) != class {
+                break;
+            }
+            p = prev;
+        }
+
+        p
+    }
+
+    // last char of the word after cursor
+    pub fn word_end(&mut self, pos: (usize, usize)) -> (usize, usize) {
+        let mut p = match self.next(pos) {
+            Some(next) => next,
+            None => return pos,
+        };
+        while self.class_at(p) == CharClass::Blank {
+            match self.next(p) {
+                Some(next) => p = next,
+                None => return p,
+            }
+        }
+        let class = self.class_at(p);
+        while let Some(next) = self.next(p) {
+            if next.0 != p.0 || self.class_at(next) != class {
+                break;
+            }
+            p = next;
+        }
+
+        p
+    }
+
+    // next grapheme starting with c in the row after cursor
+    pub fn find_char(&mut self, (row, col): (usize, usize), c: char) -> Option<(usize, usize)> {
+        let line = self.line(row);
+        let col = col.min(line.len());
+        line[col..]
+            .grapheme_indices(true)
+            .skip(1)
+            .find(|(_, g)| g.starts_with(c))
+            .map(|(i, _)| (row, col + i))
+    }
+
+    // (start, end) of the graphemes of the same class around pos in the row,
+    // like the word under the mouse pointer
+    pub fn word_at(&mut self, (row, col): (usize, usize)) -> ((usize, usize), (usize, usize)) {
+        let word_chars = self.word_chars;
+        let class_of = |g: &str| {
+            g.chars()
+                .next()
+                .map_or(CharClass::Blank, |c| char_class(c, word_chars))
+        };
+        let line = self.line(row);
+        let col = col.min(line.len());
+        let class = line[col..]
+            .graphemes(true)
+            .next()
+            .map_or(CharClass::Blank, class_of);
+        let start = line[..col]
+            .grapheme_indices(true)
+            .rev()
+            .take_while(|(_, g)| class_of(g) == class)
+            .last()
+            .map_or(col, |(i, _)| i);
+        let end = line[col..]
+            .grapheme_indices(true)
+            .take_while(|(_, g)| class_of(g) == class)
+            .last()
+            .map_or(col, |(i, g)| col + i + g.len());
+
+        ((row, start), (row, end))
+    }
+
+    // the blank row after the paragraph, or the end of the content
+    pub fn paragraph_forward(&mut self, (row, _): (usize, usize)) -> (usize, usize) {
+        let last_row = self.content.num_rows().saturating_sub(1);
+        let mut in_paragraph = false;
+        for r in row..=last_row {
+            if !self.is_blank_row(r) {
+                in_paragraph = true;
+            } else if in_paragraph {
+                return (r, 0);
+            }
+        }
+
+        self.line_end(last_row)
+    }
+
+    // the blank row before the paragraph, or the start of the content
+    pub fn paragraph_backward(&mut self, (row, _): (usize, usize)) -> (usize, usize) {
+        let row = row.min(self.content.num_rows().saturating_sub(1));
+        let mut in_paragraph = false;
+        for r in (0..=row).rev() {
+            if !self.is_blank_row(r) {
+                in_paragraph = true;
+            } else if in_paragraph {
+                return (r, 0);
+            }
+        }
+
+        (0, 0)
+    }
+
+    pub fn line_end(&mut self, row: usize) -> (usize, usize) {
+        (row, self.line(row).len())
+    }
+
+    // position after the grapheme at pos, or pos at the end of the row
+    pub fn char_end(&mut self, (row, col): (usize, usize)) -> (usize, usize) {
+        match self.grapheme_len((row, col)) {
+            Some(len) => (row, col + len),
+            None => (row, col),
+        }
+    }
+}
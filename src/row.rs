@@ -1,130 +1,180 @@
-use crate::TAB_STOP;
-use std::{error, fmt};
-
-#[derive(Debug)]
-enum Error {
-    Read(String),
-    Write(String),
-}
-
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "row: {}", self)
-    }
-}
-
-impl error::Error for Error {}
-
-impl Error {
-    #[allow(dead_code)]
-    fn new_read(msg: String) -> Self {
-        Self::Read(msg)
-    }
-    fn new_write(msg: String) -> Self {
-        Self::Write(msg)
-    }
-}
+use crate::syntax::{Highlight, Syntax};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
+// indices of Row are byte offsets of raw.
+// they have to be on grapheme cluster boundaries,
+// render index is a column on the screen.
 pub struct Row {
     pub raw: String,
     pub render: String,
+    // highlight of each char of render
+    pub hl: Vec<Highlight>,
+    // whether the previous row ends inside of multiline comment
+    pub prev_open_comment: bool,
+    // whether this row ends inside of multiline comment
+    pub hl_open_comment: bool,
+    syntax: Option<&'static Syntax>,
+    tab_stop: usize,
 }
 
 impl Row {
-    pub fn new<T>(raw: T) -> Self
+    pub fn with_tab_stop<T>(raw: T, tab_stop: usize) -> Self
     where
         T: Into<String> + Clone,
     {
         let raw = raw.into();
         let render = String::with_capacity(raw.len());
 
-        let mut row = Self { raw, render };
+        let mut row = Self {
+            raw,
+            render,
+            hl: Vec::new(),
+            prev_open_comment: false,
+            hl_open_comment: false,
+            syntax: None,
+            tab_stop,
+        };
         row.update_render();
 
         row
     }
 
-    pub fn insert(&mut self, n: usize, c: char) -> Result<(), Box<dyn error::Error>> {
-        if n <= self.raw.len() {
-            // O(n) operation
-            self.raw.insert(n, c);
-            self.update_render();
-            Ok(())
-        } else {
-            let msg = format!("failed insert index: {}, char: {}", n, c);
-            Err(Box::new(Error::new_write(msg)))
-        }
-    }
-
-    pub fn delete(&mut self, n: usize) -> Result<(), Box<dyn error::Error>> {
-        if n <= self.raw.len() {
-            self.raw.remove(n);
-            self.update_render();
-            Ok(())
-        } else {
-            let c = self.raw.chars().nth(n).unwrap_or('\0');
-            let msg = format!("failed insert index: {}, char: {}", n, c);
-            Err(Box::new(Error::new_write(msg)))
-        }
-    }
-
-    pub fn split(&self, pivot: usize) -> Result<(Row, Row), Box<dyn error::Error>> {
-        if pivot <= self.raw.len() {
-            let (first, second) = self.raw.split_at(pivot);
-            Ok((Row::new(first), Row::new(second)))
-        } else {
-            let msg = format!("failed split index out of range. index: {}", &pivot);
-            Err(Box::new(Error::new_write(msg)))
-        }
+    pub fn tab_stop(&self) -> usize {
+        self.tab_stop
     }
 
     pub fn update_render(&mut self) {
         let mut render = String::new();
         let mut index = 0;
-        (&self.raw).chars().for_each(|c| {
-            if c == '\t' {
-                render.push(' ');
-                index += 1;
-                while index % TAB_STOP != 0 {
-                    render.push(' ');
-                    index += 1;
-                }
+        self.raw.graphemes(true).for_each(|g| {
+            let width = grapheme_width(g, index, self.tab_stop);
+            if g == "\t" {
+                render.push_str(&" ".repeat(width));
             } else {
-                render.push(c);
-                index += 1;
+                render.push_str(g);
             }
+            index += width;
         });
 
         self.render = render;
+        self.update_highlight();
+    }
+
+    pub fn set_syntax(&mut self, syntax: Option<&'static Syntax>) {
+        self.syntax = syntax;
+        self.update_highlight();
+    }
+
+    pub fn update_highlight(&mut self) {
+        match self.syntax {
+            Some(syntax) => {
+                let (hl, open_comment) = syntax.highlight(&self.render, self.prev_open_comment);
+                self.hl = hl;
+                self.hl_open_comment = open_comment;
+            }
+            None => {
+                self.hl = vec![Highlight::Normal; self.render.chars().count()];
+                self.hl_open_comment = false;
+            }
+        }
     }
 
     pub fn convert_index_raw_to_render(&self, raw_index: usize) -> usize {
         let mut render_index = 0;
-        for c in self.raw.chars().take(raw_index) {
-            if c == '\t' {
-                render_index += TAB_STOP - (render_index % TAB_STOP);
-            } else {
-                render_index += 1;
+        for (i, g) in self.raw.grapheme_indices(true) {
+            if raw_index <= i {
+                break;
             }
+            render_index += grapheme_width(g, render_index, self.tab_stop);
         }
 
-        render_index.into()
+        render_index
     }
 
+    // return start of the grapheme cluster displayed at render_index
     pub fn convert_index_render_to_raw(&self, render_index: usize) -> usize {
         let mut index = 0usize;
-        for (i, c) in self.raw.chars().enumerate() {
-            if c == '\t' {
-                index += TAB_STOP - (index % TAB_STOP);
-            } else {
-                index += 1;
-            }
+        for (i, g) in self.raw.grapheme_indices(true) {
+            index += grapheme_width(g, index, self.tab_stop);
 
             if index > render_index {
-                return i.into();
+                return i;
             }
         }
 
         self.raw.len()
     }
+
+    // return index of hl (char index of render) corresponding to raw_index
+    pub fn convert_index_raw_to_hl(&self, raw_index: usize) -> usize {
+        let mut render_index = 0;
+        let mut hl_index = 0;
+        for (i, g) in self.raw.grapheme_indices(true) {
+            if raw_index <= i {
+                break;
+            }
+            let width = grapheme_width(g, render_index, self.tab_stop);
+            // tab is rendered as spaces
+            hl_index += if g == "\t" { width } else { g.chars().count() };
+            render_index += width;
+        }
+
+        hl_index
+    }
+
+    // render indices where screen lines start when the row is wrapped at width.
+    // a column for the cursor at the end of the row is also laid out.
+    pub fn wrap_starts(&self, width: usize) -> Vec<usize> {
+        let mut starts = vec![0];
+        if width == 0 {
+            return starts;
+        }
+
+        let mut line_start = 0;
+        let mut render_index = 0;
+        for g in self.render.graphemes(true) {
+            let g_width = g.width();
+            // wide char which doesn't fit moves to the next line
+            if line_start < render_index && line_start + width < render_index + g_width {
+                line_start = render_index;
+                starts.push(line_start);
+            }
+            render_index += g_width;
+        }
+        if line_start + width <= render_index {
+            starts.push(render_index);
+        }
+
+        starts
+    }
+
+    // return start of the grapheme cluster before n
+    pub fn prev_boundary(&self, n: usize) -> usize {
+        self.raw
+            .grapheme_indices(true)
+            .map(|(i, _)| i)
+            .take_while(|i| *i < n)
+            .last()
+            .unwrap_or(0)
+    }
+
+    // return start of the grapheme cluster after n
+    pub fn next_boundary(&self, n: usize) -> usize {
+        self.raw
+            .grapheme_indices(true)
+            .map(|(i, _)| i)
+            .find(|i| n < *i)
+            .unwrap_or(self.raw.len())
+    }
+}
+
+// width of grapheme cluster on the screen.
+// tab width depends on the column where it's displayed.
+pub fn grapheme_width(g: &str, render_index: usize, tab_stop: usize) -> usize {
+    if g == "\t" {
+        tab_stop - (render_index % tab_stop)
+    } else {
+        g.width()
+    }
 }
@@ -0,0 +1,245 @@
+use std::fmt::Write;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// attributes of a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    // ANSI foreground color code
+    pub fg: u8,
+    pub bold: bool,
+    pub reverse: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fg: 39,
+            bold: false,
+            reverse: false,
+        }
+    }
+}
+
+impl Style {
+    pub fn fg(fg: u8) -> Self {
+        Self {
+            fg,
+            ..Self::default()
+        }
+    }
+
+    // SGR sequence which resets attributes and sets this style
+    fn sgr(&self) -> String {
+        let mut sgr = format!("\x1b[0;{}", self.fg);
+        if self.bold {
+            sgr.push_str(";1");
+        }
+        if self.reverse {
+            sgr.push_str(";7");
+        }
+        sgr.push('m');
+        sgr
+    }
+}
+
+// a grapheme on the screen.
+// the right half of a wide char is a cell with empty text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cell {
+    text: String,
+    style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            text: " ".to_string(),
+            style: Style::default(),
+        }
+    }
+}
+
+impl Cell {
+    fn is_continuation(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    fn is_wide(&self) -> bool {
+        1 < self.text.width()
+    }
+}
+
+// screen model of the terminal.
+// a frame is drawn into the back buffer and only cells differing from
+// the front buffer (what the terminal shows) are written out.
+pub struct Screen {
+    cols: usize,
+    rows: usize,
+    front: Vec<Cell>,
+    back: Vec<Cell>,
+    // (x, y) of the cursor, None if hidden
+    cursor: Option<(usize, usize)>,
+    // None if unknown
+    front_cursor: Option<Option<(usize, usize)>>,
+    // the terminal is not known to show front, redraw everything
+    invalid: bool,
+}
+
+impl Screen {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        Self {
+            cols,
+            rows,
+            front: vec![Cell::default(); cols * rows],
+            back: vec![Cell::default(); cols * rows],
+            cursor: Some((0, 0)),
+            front_cursor: None,
+            invalid: true,
+        }
+    }
+
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        *self = Self::new(cols, rows);
+    }
+
+    // redraw everything in the next frame
+    pub fn invalidate(&mut self) {
+        self.invalid = true;
+    }
+
+    // start a new frame with blank cells
+    pub fn clear(&mut self) {
+        for cell in self.back.iter_mut() {
+            cell.text.clear();
+            cell.text.push(' ');
+            cell.style = Style::default();
+        }
+    }
+
+    // draw text from (x, y) clipped at the right edge of the screen.
+    // return the width drawn.
+    pub fn put(&mut self, x: usize, y: usize, text: &str, style: Style) -> usize {
+        if self.rows <= y {
+            return 0;
+        }
+
+        let start = x;
+        let mut x = x;
+        for g in text.graphemes(true) {
+            let width = g.width();
+            if width == 0 {
+                // zero width grapheme is joined to the previous cell
+                let row = &mut self.back[y * self.cols..y * self.cols + x];
+                if let Some(cell) = row.iter_mut().rev().find(|c| !c.is_continuation()) {
+                    cell.text.push_str(g);
+                }
+                continue;
+            }
+            if self.cols < x + width {
+                break;
+            }
+
+            let i = y * self.cols + x;
+            self.back[i].text.clear();
+            self.back[i].text.push_str(g);
+            self.back[i].style = style;
+            for cell in self.back[i + 1..i + width].iter_mut() {
+                cell.text.clear();
+                cell.style = style;
+            }
+            x += width;
+        }
+
+        x - start
+    }
+
+    pub fn set_cursor(&mut self, x: usize, y: usize) {
+        self.cursor = Some((x, y));
+    }
+
+    // the cursor is out of the screen
+    pub fn hide_cursor(&mut self) {
+        self.cursor = None;
+    }
+
+    // return escape sequences which update the terminal from the front buffer to the back buffer,
+    // None if nothing is changed
+    pub fn render(&mut self) -> Option<String> {
+        let mut out = String::new();
+        if self.invalid {
+            // erase entire screen
+            out.push_str("\x1b[2J");
+        }
+
+        // position and style of the terminal cursor while writing
+        let mut pos: Option<(usize, usize)> = None;
+        let mut style: Option<Style> = None;
+        for y in 0..self.rows {
+            let row = y * self.cols..(y + 1) * self.cols;
+            let changed = self.changed_cells(row.clone());
+            for (x, i) in row.enumerate() {
+                if !changed[x] || self.back[i].is_continuation() {
+                    continue;
+                }
+                if pos != Some((x, y)) {
+                    let _ = write!(out, "\x1b[{};{}H", y + 1, x + 1);
+                }
+                let cell = &self.back[i];
+                if style != Some(cell.style) {
+                    out.push_str(&cell.style.sgr());
+                    style = Some(cell.style);
+                }
+                out.push_str(&cell.text);
+                pos = Some((x + cell.text.width().max(1), y));
+            }
+        }
+
+        if out.is_empty() && self.front_cursor == Some(self.cursor) {
+            return None;
+        }
+
+        if !out.is_empty() {
+            // hide cursor while writing
+            out = format!("\x1b[?25l{}\x1b[m", out);
+        }
+        match self.cursor {
+            Some((x, y)) => {
+                let _ = write!(out, "\x1b[{};{}H\x1b[?25h", y + 1, x + 1);
+            }
+            None => out.push_str("\x1b[?25l"),
+        }
+
+        self.front.clone_from(&self.back);
+        self.front_cursor = Some(self.cursor);
+        self.invalid = false;
+
+        Some(out)
+    }
+
+    // cells of the row which have to be written.
+    // both halves of a wide char are written together.
+    fn changed_cells(&self, row: std::ops::Range<usize>) -> Vec<bool> {
+        let start = row.start;
+        let mut changed: Vec<bool> = row
+            .clone()
+            .map(|i| self.invalid || self.front[i] != self.back[i])
+            .collect();
+        for i in row.rev() {
+            let x = i - start;
+            if !changed[x] {
+                continue;
+            }
+            let (front, back) = (&self.front[i], &self.back[i]);
+            if 0 < x && (front.is_continuation() || back.is_continuation()) {
+                changed[x - 1] = true;
+            }
+            if x + 1 < changed.len() && (front.is_wide() || back.is_wide()) {
+                changed[x + 1] = true;
+            }
+        }
+
+        changed
+    }
+}
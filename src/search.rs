@@ -0,0 +1,134 @@
+use crate::error::{self, Error};
+use crate::row::Row;
+use crate::syntax::Highlight;
+use regex::{Regex, RegexBuilder};
+
+// toggles of find and replace prompt
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchOptions {
+    // query is a regular expression, otherwise literal
+    pub regex: bool,
+    pub ignore_case: bool,
+    pub whole_word: bool,
+}
+
+impl SearchOptions {
+    // like ` [regex icase word]`, empty if no option is on
+    pub fn label(&self) -> String {
+        let flags: Vec<&str> = [
+            (self.regex, "regex"),
+            (self.ignore_case, "icase"),
+            (self.whole_word, "word"),
+        ]
+        .iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, name)| *name)
+        .collect();
+
+        if flags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", flags.join(" "))
+        }
+    }
+}
+
+// compiled query. literal query is also matched by an escaped regex.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    regex: Regex,
+    // expand `$1`, `${name}` in replacement
+    expand: bool,
+}
+
+impl Pattern {
+    pub fn new(query: &str, options: &SearchOptions) -> error::Result<Self> {
+        let mut pattern = if options.regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(options.ignore_case)
+            .build()
+            .map_err(|e| Error::Command(e.to_string()))?;
+
+        Ok(Self {
+            regex,
+            expand: options.regex,
+        })
+    }
+
+    // return (start, end) byte offsets of matches in the line
+    pub fn find_iter<'a>(&'a self, line: &'a str) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.regex.find_iter(line).map(|m| (m.start(), m.end()))
+    }
+
+    // return replacement of the match starting at start in the line
+    pub fn replacement(&self, line: &str, start: usize, replacement: &str) -> String {
+        if !self.expand {
+            return replacement.to_string();
+        }
+
+        let mut dst = String::new();
+        if let Some(caps) = self.regex.captures_at(line, start) {
+            caps.expand(replacement, &mut dst);
+        }
+        dst
+    }
+}
+
+// matches painted on the screen during search
+#[derive(Debug)]
+pub struct SearchHighlight {
+    pub pattern: Pattern,
+    // (row idx, start col idx, end col idx) of the match under cursor
+    pub current: Option<(usize, usize, usize)>,
+    pub count: MatchCount,
+}
+
+// matches counted for the status of search.
+// counting stops at a limit not to scan a large file on each key.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MatchCount {
+    // 1-origin index of the current match, 0 if it's not counted
+    pub index: usize,
+    pub total: usize,
+    // counting stopped at the limit, there may be more matches
+    pub partial: bool,
+}
+
+impl SearchHighlight {
+    // overwrite highlight of matches in the row
+    pub fn highlight_row(&self, row_idx: usize, row: &Row, hl: &mut [Highlight]) {
+        for (start, end) in self.pattern.find_iter(&row.raw) {
+            let kind = if self.current == Some((row_idx, start, end)) {
+                Highlight::CurrentMatch
+            } else {
+                Highlight::Match
+            };
+            let start = row.convert_index_raw_to_hl(start).min(hl.len());
+            let end = row.convert_index_raw_to_hl(end).min(hl.len());
+            hl[start..end].fill(kind);
+        }
+    }
+
+    // like `match 3 of 17`, or `1000+ matches` when counting stopped
+    pub fn status(&self) -> String {
+        let MatchCount {
+            index,
+            total,
+            partial,
+        } = self.count;
+        let more = if partial { "+" } else { "" };
+        match (index, total) {
+            (_, 0) if partial => "matches not counted".to_string(),
+            (_, 0) => "no match".to_string(),
+            (0, total) => format!("{}{} matches", total, more),
+            (i, total) => format!("match {} of {}{}", i, total, more),
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs, io};
+
+use crate::encoding::LineEnding;
+use crate::error::{self, Error};
+use crate::file;
+use crate::syntax::Highlight;
+use crate::MESSAGE_TIMEOUT;
+
+// settings of the project, looked up from the current directory to the root
+const PROJECT_SETTINGS: &str = ".kiroro.toml";
+
+// highlight names in `[colors]` section
+static COLOR_NAMES: &[(&str, Highlight)] = &[
+    ("normal", Highlight::Normal),
+    ("comment", Highlight::Comment),
+    ("multiline_comment", Highlight::MultilineComment),
+    ("keyword1", Highlight::Keyword1),
+    ("keyword2", Highlight::Keyword2),
+    ("string", Highlight::String),
+    ("number", Highlight::Number),
+    ("match", Highlight::Match),
+    ("current_match", Highlight::CurrentMatch),
+    ("line_number", Highlight::LineNumber),
+    ("bracket", Highlight::Bracket),
+];
+
+// ANSI foreground color codes by name
+static COLOR_CODES: &[(&str, u8)] = &[
+    ("default", 39),
+    ("black", 30),
+    ("red", 31),
+    ("green", 32),
+    ("yellow", 33),
+    ("blue", 34),
+    ("magenta", 35),
+    ("cyan", 36),
+    ("white", 37),
+    ("bright_black", 90),
+    ("bright_red", 91),
+    ("bright_green", 92),
+    ("bright_yellow", 93),
+    ("bright_blue", 94),
+    ("bright_magenta", 95),
+    ("bright_cyan", 96),
+    ("bright_white", 97),
+];
+
+// options loaded from settings files and changed by `:set`
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub tab_stop: usize,
+    // Tab key inserts spaces up to the next tab stop
+    pub expand_tabs: bool,
+    pub line_numbers: bool,
+    // line numbers relative to the cursor line, the cursor line shows its own number
+    pub relative_numbers: bool,
+    pub soft_wrap: bool,
+    // Enter keeps the indent of the line and closing brackets are dedented
+    pub auto_indent: bool,
+    // highlight the bracket matching the one at the cursor
+    pub match_brackets: bool,
+    // chars making words with alphanumerics, for word motions and deletion
+    pub word_chars: String,
+    // seconds a status message is shown
+    pub message_timeout: u64,
+    // Ctrl-Q presses to quit with unsaved changes
+    pub quit_times: u8,
+    // foreground colors overriding Highlight::color
+    colors: HashMap<Highlight, u8>,
+    // `[filetype.rust]` sections by filetype of Syntax
+    filetypes: HashMap<String, FileSettings>,
+}
+
+// settings of a file overriding the global ones.
+// given by `.editorconfig` or filetype sections, None is not specified.
+#[derive(Debug, Clone, Default)]
+pub struct FileSettings {
+    pub tab_stop: Option<usize>,
+    pub expand_tabs: Option<bool>,
+    // width of indent inserted by Tab key with expand_tabs, tab_stop if None
+    pub indent_size: Option<usize>,
+    // line ending converted to on save
+    pub end_of_line: Option<LineEnding>,
+    pub insert_final_newline: Option<bool>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub word_chars: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            tab_stop: 4,
+            expand_tabs: false,
+            line_numbers: false,
+            relative_numbers: false,
+            soft_wrap: false,
+            auto_indent: true,
+            match_brackets: true,
+            word_chars: "_".to_string(),
+            message_timeout: MESSAGE_TIMEOUT,
+            quit_times: 2,
+            colors: HashMap::new(),
+            filetypes: HashMap::new(),
+        }
+    }
+}
+
+// like `tab_stop=4 expand_tabs=false ...`
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            "tab_stop",
+            "expand_tabs",
+            "line_numbers",
+            "relative_numbers",
+            "soft_wrap",
+            "auto_indent",
+            "match_brackets",
+            "word_chars",
+            "message_timeout",
+            "quit_times",
+        ];
+        let options: Vec<String> = names
+            .iter()
+            .filter_map(|name| {
+                self.get(name)
+                    .ok()
+                    .map(|value| format!("{}={}", name, value))
+            })
+            .collect();
+        write!(f, "{}", options.join(" "))
+    }
+}
+
+impl Settings {
+    // global settings, then settings of the project
+    pub fn paths() -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = file::config_dir()
+            .map(|dir| dir.join("config.toml"))
+            .into_iter()
+            .collect();
+        if let Ok(dir) = env::current_dir() {
+            if let Some(path) = dir
+                .ancestors()
+                .map(|dir| dir.join(PROJECT_SETTINGS))
+                .find(|path| path.is_file())
+            {
+                paths.push(path);
+            }
+        }
+
+        paths
+    }
+
+    // missing file is not an error
+    pub fn load_file(&mut self, path: &Path) -> error::Result<()> {
+        match fs::read_to_string(path) {
+            Ok(text) => self.load(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(Error::Io(e)),
+        }
+    }
+
+    // apply lines like `tab_stop = 8`.
+    // keys after `[colors]` are highlight names, keys after `[filetype.<name>]` are
+    // settings of the filetype. `#` out of quotes starts a comment.
+    pub fn load(&mut self, text: &str) -> error::Result<()> {
+        let mut errors = Vec::new();
+        let mut section = String::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+
+            let res = match line.split_once('=') {
+                Some((name, value)) => {
+                    let name = match section.as_str() {
+                        "" => name.trim().to_string(),
+                        section => format!("{}.{}", section, name.trim()),
+                    };
+                    self.set(&name, value.trim().trim_matches('"'))
+                }
+                None => Err(format!("expected `<name> = <value>`, found `{}`", line)),
+            };
+            if let Err(msg) = res {
+                errors.push(format!("line {}: {}", i + 1, msg));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Config(errors.join(", ")))
+        }
+    }
+
+    // apply an argument of `:set` like `tab_stop=8`, `expand_tabs`, `noexpand_tabs` or `tab_stop?`.
+    // return `name=value` to show
+    pub fn apply(&mut self, arg: &str) -> Result<Option<String>, String> {
+        if let Some((name, value)) = arg.split_once('=') {
+            self.set(name, value)?;
+            return Ok(None);
+        }
+
+        let name = arg.strip_suffix('?').unwrap_or(arg);
+        let value = match self.get(name) {
+            Ok(value) => value,
+            // `noexpand_tabs`
+            Err(e) => match name.strip_prefix("no") {
+                Some(name) if self.get(name).is_ok_and(|v| v == "true" || v == "false") => {
+                    self.set(name, "false")?;
+                    return Ok(None);
+                }
+                _ => return Err(e),
+            },
+        };
+        if name == arg && (value == "true" || value == "false") {
+            self.set(name, "true")?;
+            Ok(None)
+        } else {
+            Ok(Some(format!("{}={}", name, value)))
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "tab_stop" => match parse_number(name, value)? {
+                0 => return Err("tab_stop must be positive".to_string()),
+                n => self.tab_stop = n,
+            },
+            "expand_tabs" => self.expand_tabs = parse_bool(name, value)?,
+            "line_numbers" => self.line_numbers = parse_bool(name, value)?,
+            "relative_numbers" => self.relative_numbers = parse_bool(name, value)?,
+            "soft_wrap" => self.soft_wrap = parse_bool(name, value)?,
+            "auto_indent" => self.auto_indent = parse_bool(name, value)?,
+            "match_brackets" => self.match_brackets = parse_bool(name, value)?,
+            "word_chars" => self.word_chars = value.to_string(),
+            "message_timeout" => self.message_timeout = parse_number(name, value)?,
+            "quit_times" => match parse_number(name, value)? {
+                0 => return Err("quit_times must be positive".to_string()),
+                n => self.quit_times = n,
+            },
+            _ if name.starts_with("filetype.") => {
+                let (filetype, key) = filetype_name(name)?;
+                self.filetypes
+                    .entry(filetype.to_string())
+                    .or_default()
+                    .set(key, value)?;
+            }
+            _ => {
+                let hl = color_name(name)?;
+                let code = COLOR_CODES
+                    .iter()
+                    .find(|(color, _)| *color == value)
+                    .map(|(_, code)| *code)
+                    .or_else(|| value.parse().ok())
+                    .ok_or_else(|| format!("invalid color for {}: `{}`", name, value))?;
+                self.colors.insert(hl, code);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<String, String> {
+        let value = match name {
+            "tab_stop" => self.tab_stop.to_string(),
+            "expand_tabs" => self.expand_tabs.to_string(),
+            "line_numbers" => self.line_numbers.to_string(),
+            "relative_numbers" => self.relative_numbers.to_string(),
+            "soft_wrap" => self.soft_wrap.to_string(),
+            "auto_indent" => self.auto_indent.to_string(),
+            "match_brackets" => self.match_brackets.to_string(),
+            "word_chars" => self.word_chars.clone(),
+            "message_timeout" => self.message_timeout.to_string(),
+            "quit_times" => self.quit_times.to_string(),
+            _ if name.starts_with("filetype.") => {
+                let (filetype, key) = filetype_name(name)?;
+                let settings = self.filetypes.get(filetype).cloned().unwrap_or_default();
+                settings.get(key)?
+            }
+            _ => self.color(color_name(name)?).to_string(),
+        };
+
+        Ok(value)
+    }
+
+    // width of line numbers and a space for the number of rows.
+    // 0 if line numbers are off or the window is too narrow.
+    pub fn gutter_width(&self, num_rows: usize, cols: usize) -> usize {
+        if !self.line_numbers && !self.relative_numbers {
+            return 0;
+        }
+        let width = num_rows.max(1).to_string().len() + 1;
+        if width < cols {
+            width
+        } else {
+            0
+        }
+    }
+
+    // ANSI foreground color code of the highlight
+    pub fn color(&self, hl: Highlight) -> u8 {
+        self.colors.get(&hl).copied().unwrap_or(hl.color())
+    }
+
+    pub fn filetype(&self, filetype: Option<&str>) -> FileSettings {
+        filetype
+            .and_then(|filetype| self.filetypes.get(filetype))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl FileSettings {
+    // fill unspecified settings with other
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            tab_stop: self.tab_stop.or(other.tab_stop),
+            expand_tabs: self.expand_tabs.or(other.expand_tabs),
+            indent_size: self.indent_size.or(other.indent_size),
+            end_of_line: self.end_of_line.or(other.end_of_line),
+            insert_final_newline: self.insert_final_newline.or(other.insert_final_newline),
+            trim_trailing_whitespace: self
+                .trim_trailing_whitespace
+                .or(other.trim_trailing_whitespace),
+            word_chars: self.word_chars.or(other.word_chars),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "tab_stop" | "indent_size" => {
+                let n = match parse_number(name, value)? {
+                    0 => return Err(format!("{} must be positive", name)),
+                    n => Some(n),
+                };
+                if name == "tab_stop" {
+                    self.tab_stop = n;
+                } else {
+                    self.indent_size = n;
+                }
+            }
+            "expand_tabs" => self.expand_tabs = Some(parse_bool(name, value)?),
+            "end_of_line" => {
+                self.end_of_line = match value {
+                    "lf" => Some(LineEnding::Lf),
+                    "crlf" => Some(LineEnding::CrLf),
+                    _ => return Err(format!("end_of_line must be lf or crlf: `{}`", value)),
+                }
+            }
+            "insert_final_newline" => self.insert_final_newline = Some(parse_bool(name, value)?),
+            "trim_trailing_whitespace" => {
+                self.trim_trailing_whitespace = Some(parse_bool(name, value)?)
+            }
+            "word_chars" => self.word_chars = Some(value.to_string()),
+            _ => return Err(format!("unknown option `{}`", name)),
+        }
+
+        Ok(())
+    }
+
+    // `unset` if not specified
+    fn get(&self, name: &str) -> Result<String, String> {
+        let value = match name {
+            "tab_stop" => self.tab_stop.map(|n| n.to_string()),
+            "indent_size" => self.indent_size.map(|n| n.to_string()),
+            "expand_tabs" => self.expand_tabs.map(|b| b.to_string()),
+            "end_of_line" => self.end_of_line.map(|e| e.name().to_lowercase()),
+            "insert_final_newline" => self.insert_final_newline.map(|b| b.to_string()),
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace.map(|b| b.to_string()),
+            "word_chars" => self.word_chars.clone(),
+            _ => return Err(format!("unknown option `{}`", name)),
+        };
+
+        Ok(value.unwrap_or_else(|| "unset".to_string()))
+    }
+}
+
+// remove a comment starting with `#` at the start of the line or after a space,
+// `#` in quotes or in a word like `alt-#` is kept
+pub fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes && prev.is_whitespace() => return &line[..i],
+            _ => {}
+        }
+        prev = c;
+    }
+
+    line
+}
+
+// `filetype.rust.tab_stop` to (`rust`, `tab_stop`)
+fn filetype_name(name: &str) -> Result<(&str, &str), String> {
+    name.strip_prefix("filetype.")
+        .and_then(|name| name.split_once('.'))
+        .ok_or_else(|| format!("unknown option `{}`", name))
+}
+
+// `colors.comment` to Highlight::Comment
+fn color_name(name: &str) -> Result<Highlight, String> {
+    name.strip_prefix("colors.")
+        .and_then(|hl| COLOR_NAMES.iter().find(|(n, _)| *n == hl))
+        .map(|(_, hl)| *hl)
+        .ok_or_else(|| format!("unknown option `{}`", name))
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be true or false: `{}`", name, value))
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be a number: `{}`", name, value))
+}
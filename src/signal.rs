@@ -0,0 +1,30 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// set by SIGWINCH handler, cleared by take_resized
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_resize(_: libc::c_int) {
+    RESIZED.store(true, Ordering::SeqCst);
+}
+
+// install SIGWINCH handler.
+// SA_RESTART is not set, so a blocking read of stdin fails with EINTR on resize.
+pub fn watch_resize() -> io::Result<()> {
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_resize as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(libc::SIGWINCH, &action, std::ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+// return whether the terminal was resized since the last call
+pub fn take_resized() -> bool {
+    RESIZED.swap(false, Ordering::SeqCst)
+}
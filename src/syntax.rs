@@ -0,0 +1,281 @@
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Highlight {
+    Normal,
+    Comment,
+    MultilineComment,
+    Keyword1,
+    Keyword2,
+    String,
+    Number,
+    Match,
+    // search match under cursor, drawn in reverse video
+    CurrentMatch,
+    // gutter of line numbers
+    LineNumber,
+    // bracket matching the one at the cursor, drawn in reverse video
+    Bracket,
+}
+
+impl Highlight {
+    // ANSI foreground color code
+    pub fn color(&self) -> u8 {
+        match self {
+            Self::Normal => 39,
+            Self::Comment | Self::MultilineComment => 36,
+            Self::Keyword1 => 33,
+            Self::Keyword2 => 32,
+            Self::String => 35,
+            Self::Number => 31,
+            Self::Match | Self::CurrentMatch => 34,
+            Self::LineNumber => 90,
+            Self::Bracket => 96,
+        }
+    }
+}
+
+pub const HL_HIGHLIGHT_NUMBERS: u8 = 1 << 0;
+pub const HL_HIGHLIGHT_STRINGS: u8 = 1 << 1;
+// line starts with `#` is highlighted as keyword1
+pub const HL_HIGHLIGHT_HEADINGS: u8 = 1 << 2;
+
+pub struct Syntax {
+    pub filetype: &'static str,
+    // file extensions (start with `.`) or file names
+    pub filematch: &'static [&'static str],
+    pub keywords1: &'static [&'static str],
+    pub keywords2: &'static [&'static str],
+    pub string_delimiters: &'static [char],
+    pub singleline_comment_start: &'static str,
+    pub multiline_comment_start: &'static str,
+    pub multiline_comment_end: &'static str,
+    pub flags: u8,
+    // a line ending with one of these indents the next line
+    pub indent_after: &'static [char],
+    // a line starting with one of these is dedented
+    pub dedent_before: &'static [char],
+}
+
+pub static HLDB: &[Syntax] = &[
+    Syntax {
+        filetype: "rust",
+        filematch: &[".rs"],
+        keywords1: &[
+            "as", "break", "const", "continue", "crate", "else", "enum", "extern", "fn", "for",
+            "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+            "return", "self", "Self", "static", "struct", "super", "trait", "type", "unsafe",
+            "use", "where", "while", "async", "await", "dyn",
+        ],
+        keywords2: &[
+            "bool", "char", "str", "String", "u8", "u16", "u32", "u64", "u128", "usize", "i8",
+            "i16", "i32", "i64", "i128", "isize", "f32", "f64", "Option", "Result", "Some", "None",
+            "Ok", "Err", "Box", "Vec", "true", "false",
+        ],
+        string_delimiters: &['"'],
+        singleline_comment_start: "//",
+        multiline_comment_start: "/*",
+        multiline_comment_end: "*/",
+        flags: HL_HIGHLIGHT_NUMBERS | HL_HIGHLIGHT_STRINGS,
+        indent_after: &['{', '(', '['],
+        dedent_before: &['}', ')', ']'],
+    },
+    Syntax {
+        filetype: "c",
+        filematch: &[".c", ".h", ".cpp", ".hpp", ".cc"],
+        keywords1: &[
+            "switch", "if", "while", "for", "break", "continue", "return", "else", "struct",
+            "union", "typedef", "static", "enum", "class", "case", "default", "do", "goto",
+            "sizeof", "const", "volatile", "extern", "#include", "#define", "#ifdef", "#ifndef",
+            "#endif",
+        ],
+        keywords2: &[
+            "int", "long", "double", "float", "char", "unsigned", "signed", "void", "short",
+            "bool", "size_t",
+        ],
+        string_delimiters: &['"', '\''],
+        singleline_comment_start: "//",
+        multiline_comment_start: "/*",
+        multiline_comment_end: "*/",
+        flags: HL_HIGHLIGHT_NUMBERS | HL_HIGHLIGHT_STRINGS,
+        indent_after: &['{', '(', '['],
+        dedent_before: &['}', ')', ']'],
+    },
+    Syntax {
+        filetype: "markdown",
+        filematch: &[".md", ".markdown"],
+        keywords1: &[],
+        keywords2: &[],
+        // inline code
+        string_delimiters: &['`'],
+        singleline_comment_start: "",
+        multiline_comment_start: "<!--",
+        multiline_comment_end: "-->",
+        flags: HL_HIGHLIGHT_STRINGS | HL_HIGHLIGHT_HEADINGS,
+        indent_after: &[],
+        dedent_before: &[],
+    },
+    Syntax {
+        filetype: "python",
+        filematch: &[".py"],
+        keywords1: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+            "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+            "with", "yield",
+        ],
+        keywords2: &[
+            "True", "False", "None", "self", "int", "float", "str", "bool", "list", "dict",
+            "tuple", "set", "bytes",
+        ],
+        string_delimiters: &['"', '\''],
+        singleline_comment_start: "#",
+        multiline_comment_start: "",
+        multiline_comment_end: "",
+        flags: HL_HIGHLIGHT_NUMBERS | HL_HIGHLIGHT_STRINGS,
+        indent_after: &[':', '{', '(', '['],
+        dedent_before: &['}', ')', ']'],
+    },
+];
+
+impl Syntax {
+    // select syntax by filename
+    pub fn select(filename: &str) -> Option<&'static Syntax> {
+        let name = Path::new(filename)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(filename);
+
+        HLDB.iter().find(|syntax| {
+            syntax.filematch.iter().any(|pattern| {
+                if pattern.starts_with('.') {
+                    name.ends_with(pattern)
+                } else {
+                    name == *pattern
+                }
+            })
+        })
+    }
+
+    // return highlight of each char of `render` and
+    // whether multiline comment is still open at the end of the row.
+    // `open_comment` is whether the previous row ends inside of multiline comment.
+    pub fn highlight(&self, render: &str, open_comment: bool) -> (Vec<Highlight>, bool) {
+        let chars: Vec<char> = render.chars().collect();
+        let mut hl = vec![Highlight::Normal; chars.len()];
+
+        if self.flags & HL_HIGHLIGHT_HEADINGS != 0 && !open_comment && render.starts_with('#') {
+            return (vec![Highlight::Keyword1; chars.len()], false);
+        }
+
+        let scs: Vec<char> = self.singleline_comment_start.chars().collect();
+        let mcs: Vec<char> = self.multiline_comment_start.chars().collect();
+        let mce: Vec<char> = self.multiline_comment_end.chars().collect();
+        let starts_with =
+            |i: usize, pat: &[char]| -> bool { !pat.is_empty() && chars[i..].starts_with(pat) };
+
+        let mut prev_sep = true;
+        let mut in_string: Option<char> = None;
+        let mut in_comment = open_comment;
+
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let prev_hl = if 0 < i { hl[i - 1] } else { Highlight::Normal };
+
+            if in_string.is_none() && !in_comment && starts_with(i, &scs) {
+                hl[i..].fill(Highlight::Comment);
+                break;
+            }
+
+            if in_string.is_none() && !mcs.is_empty() && !mce.is_empty() {
+                if in_comment {
+                    hl[i] = Highlight::MultilineComment;
+                    if starts_with(i, &mce) {
+                        hl[i..i + mce.len()].fill(Highlight::MultilineComment);
+                        i += mce.len();
+                        in_comment = false;
+                        prev_sep = true;
+                    } else {
+                        i += 1;
+                    }
+                    continue;
+                } else if starts_with(i, &mcs) {
+                    hl[i..i + mcs.len()].fill(Highlight::MultilineComment);
+                    i += mcs.len();
+                    in_comment = true;
+                    continue;
+                }
+            }
+
+            if self.flags & HL_HIGHLIGHT_STRINGS != 0 {
+                if let Some(delimiter) = in_string {
+                    hl[i] = Highlight::String;
+                    // escaped char
+                    if c == '\\' && i + 1 < chars.len() {
+                        hl[i + 1] = Highlight::String;
+                        i += 2;
+                        continue;
+                    }
+                    if c == delimiter {
+                        in_string = None;
+                    }
+                    i += 1;
+                    prev_sep = true;
+                    continue;
+                } else if self.string_delimiters.contains(&c) {
+                    in_string = Some(c);
+                    hl[i] = Highlight::String;
+                    i += 1;
+                    continue;
+                }
+            }
+
+            if self.flags & HL_HIGHLIGHT_NUMBERS != 0
+                && ((c.is_ascii_digit() && (prev_sep || prev_hl == Highlight::Number))
+                    || (c == '.' && prev_hl == Highlight::Number))
+            {
+                hl[i] = Highlight::Number;
+                i += 1;
+                prev_sep = false;
+                continue;
+            }
+
+            if prev_sep {
+                if let Some((len, kind)) = self.match_keyword(&chars[i..]) {
+                    hl[i..i + len].fill(kind);
+                    i += len;
+                    prev_sep = false;
+                    continue;
+                }
+            }
+
+            prev_sep = is_separator(c);
+            i += 1;
+        }
+
+        (hl, in_comment)
+    }
+
+    fn match_keyword(&self, chars: &[char]) -> Option<(usize, Highlight)> {
+        let keywords1 = self.keywords1.iter().map(|k| (k, Highlight::Keyword1));
+        let keywords2 = self.keywords2.iter().map(|k| (k, Highlight::Keyword2));
+
+        keywords1.chain(keywords2).find_map(|(keyword, kind)| {
+            let len = keyword.chars().count();
+            let matched = chars.len() >= len
+                && keyword.chars().zip(chars.iter()).all(|(k, c)| k == *c)
+                && chars.get(len).is_none_or(|c| is_separator(*c));
+
+            if matched {
+                Some((len, kind))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == '\0' || ",.()+-/*=~%<>[];{}:&|!?".contains(c)
+}
@@ -0,0 +1,415 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, stdin, stdout, Read, Stdin, Stdout, Write};
+use std::panic;
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use termion::event::{Event, Key};
+use termion::input::{Events, MouseTerminal, TermRead};
+use termion::raw::{IntoRawMode, RawTerminal};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::signal;
+
+// input, output and size of the terminal the editor runs on
+pub trait Terminal {
+    // wait for the next input event.
+    // fail with `Interrupted` when the terminal is resized while waiting.
+    fn read_event(&mut self) -> io::Result<Event>;
+
+    // whether the terminal was resized since the last call
+    fn take_resized(&mut self) -> bool;
+
+    // return (cols, rows)
+    fn size(&mut self) -> io::Result<(u16, u16)>;
+
+    // write escape sequences and text, and flush them
+    fn write(&mut self, s: &str) -> io::Result<()>;
+}
+
+// mode of the terminal before raw mode, restored by the panic hook
+static ORIGINAL_MODE: OnceLock<libc::termios> = OnceLock::new();
+
+// restore the terminal before a panic message is printed,
+// so that the message is readable and the shell works after a crash
+fn install_panic_hook() {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(mode) = ORIGINAL_MODE.get() {
+            // SAFETY: mode is a valid termios got by tcgetattr
+            unsafe {
+                libc::tcsetattr(libc::STDOUT_FILENO, libc::TCSANOW, mode);
+            }
+        }
+        // disable mouse reporting, show cursor, reset attributes and clear the screen
+        print!("\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l");
+        print!("\x1b[?25h\x1b[m\x1b[2J\x1b[H");
+        let _ = io::stdout().flush();
+        hook(info);
+    }));
+}
+
+// the terminal of stdin and stdout in raw mode
+pub struct TermionTerminal {
+    out: MouseTerminal<RawTerminal<Stdout>>,
+    events: Events<Stdin>,
+}
+
+impl TermionTerminal {
+    pub fn new() -> io::Result<Self> {
+        // change rawmode
+        // TODO: describe canonical mode and raw mode
+        // dropped stdout, restore original state
+        // into_raw_mode invoke libc::cfmakeraw()
+        // cfmakeraw() set like `version7` driver's row mode
+        //
+        // specifically, set flags below
+        // termios_p->c_iflag &= ~(IGNBRK | BRKINT | PARMRK | ISTRIP | INLCR | IGNCR | ICRNL | IXON)
+        // termios_p->c_oflag &= ~OPOST;
+        // termios_p->c_lflag &= ~(ECHO | ECHONL | ICANON | ISIG | IEXTEN);
+        // termios_p->c_cflag &= ~(CSIZE | PARENB);
+        // termios_p->c_cflag |= CS8;
+        //
+        // into_raw_mode: https://github.com/redox-os/termion/blob/dce5e7500fd709987f9bf8f3911e4daa61d0ad14/src/raw.rs#L101-L114
+        // raw_terminal_attr: https://github.com/redox-os/termion/blob/8054e082b01c3f45f89f0db96bc374f1e378deb1/src/sys/unix/attr.rs#L17-L19
+        // SAFETY: termios is plain data filled by tcgetattr
+        unsafe {
+            let mut mode: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDOUT_FILENO, &mut mode) == 0
+                && ORIGINAL_MODE.set(mode).is_ok()
+            {
+                install_panic_hook();
+            }
+        }
+        // mouse reporting is enabled until dropped
+        let out = MouseTerminal::from(stdout().into_raw_mode()?);
+
+        Ok(Self {
+            out,
+            events: stdin().events(),
+        })
+    }
+
+    // return (col, row)
+    #[allow(dead_code)]
+    fn get_cursor_pos(&mut self) -> io::Result<(u16, u16)> {
+        // get terminal status by \x1b[6n (https://vt100.net/docs/vt100-ug/chapter3.html#DSR)
+        // temrinal responses to stdin (http://vt100.net/docs/vt100-ug/chapter3.html#CPR)
+        // like `\x1b[{row};{col}R`
+        // after print \x1b[6n, parse response
+        print!("\x1b[6n");
+        print!("\r\n");
+        self.out.flush()?;
+
+        let mut response = Vec::<u8>::new();
+
+        // parse terminal response
+        // get cols and rows from `\x1b[{cols};{rows}`
+        for b in stdin().lock().bytes() {
+            match b? {
+                b'\x1b' | b'[' => {}
+                b'R' => break,
+                b => response.push(b),
+            }
+        }
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid cursor position");
+        let row_col = String::from_utf8(response).map_err(|_| invalid())?;
+        let (row, col) = row_col.split_once(';').ok_or_else(invalid)?;
+        let row = row.parse::<u16>().map_err(|_| invalid())?;
+        let col = col.parse::<u16>().map_err(|_| invalid())?;
+
+        Ok((col, row))
+    }
+}
+
+impl Terminal for TermionTerminal {
+    fn read_event(&mut self) -> io::Result<Event> {
+        match self.events.next() {
+            Some(event) => event,
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stdin is closed",
+            )),
+        }
+    }
+
+    fn take_resized(&mut self) -> bool {
+        signal::take_resized()
+    }
+
+    fn size(&mut self) -> io::Result<(u16, u16)> {
+        match termion::terminal_size() {
+            Ok(ts) => Ok(ts),
+            Err(_) => {
+                // get termsize manually
+                // move cursor bottom right
+                // \x1b[nC (n: natural number) move cursor to right direction amount of n
+                // \x1b[nB (n: natural number) move cursor to bottom direction amount of n
+                // print!("\x1b[999C\x1b[999B");
+                print!("{}", termion::cursor::Goto(999, 999));
+                self.out.flush()?;
+
+                // then, get cursor position
+                // get_cursor_pos()
+                termion::cursor::DetectCursorPos::cursor_pos(&mut self.out)
+            }
+        }
+    }
+
+    fn write(&mut self, s: &str) -> io::Result<()> {
+        self.out.write_all(s.as_bytes())?;
+        self.out.flush()
+    }
+}
+
+enum Input {
+    Event(Event),
+    Resize(u16, u16),
+}
+
+struct VirtualState {
+    cols: usize,
+    rows: usize,
+    // text of each cell, the right half of a wide char is empty
+    cells: Vec<String>,
+    // (x, y)
+    cursor: (usize, usize),
+    cursor_visible: bool,
+    input: VecDeque<Input>,
+    resized: bool,
+    // the scripted input ran out, later output like clearing on exit is dropped
+    // to keep the last screen the editor drew
+    closed: bool,
+}
+
+impl VirtualState {
+    fn new(cols: usize, rows: usize) -> Self {
+        Self {
+            cols,
+            rows,
+            cells: vec![" ".to_string(); cols * rows],
+            cursor: (0, 0),
+            cursor_visible: true,
+            input: VecDeque::new(),
+            resized: false,
+            closed: false,
+        }
+    }
+
+    // interpret text and escape sequences written by the editor
+    fn write(&mut self, s: &str) {
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' => {
+                    self.print(&text);
+                    text.clear();
+                    match chars.next() {
+                        // CSI, parameters end with a final byte
+                        Some('[') => {
+                            let mut params = String::new();
+                            for c in chars.by_ref() {
+                                if ('\x40'..='\x7e').contains(&c) {
+                                    self.csi(&params, c);
+                                    break;
+                                }
+                                params.push(c);
+                            }
+                        }
+                        // OSC ends with BEL or ST
+                        Some(']') => {
+                            while let Some(c) = chars.next() {
+                                if c == '\x07' {
+                                    break;
+                                }
+                                if c == '\x1b' {
+                                    chars.next();
+                                    break;
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                '\r' | '\n' => {
+                    self.print(&text);
+                    text.clear();
+                    if c == '\r' {
+                        self.cursor.0 = 0;
+                    } else {
+                        self.cursor.1 = (self.cursor.1 + 1).min(self.rows.saturating_sub(1));
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+        self.print(&text);
+    }
+
+    fn csi(&mut self, params: &str, command: char) {
+        let numbers: Vec<usize> = params.split(';').map(|n| n.parse().unwrap_or(0)).collect();
+        let n = |i: usize| numbers.get(i).copied().unwrap_or(0);
+        match (params, command) {
+            ("?25", 'l') => self.cursor_visible = false,
+            ("?25", 'h') => self.cursor_visible = true,
+            (_, 'H') => {
+                let y = n(0).max(1) - 1;
+                let x = n(1).max(1) - 1;
+                self.cursor = (x.min(self.cols), y.min(self.rows.saturating_sub(1)));
+            }
+            ("2", 'J') => self
+                .cells
+                .iter_mut()
+                .for_each(|cell| *cell = " ".to_string()),
+            (_, 'K') => {
+                let (x, y) = self.cursor;
+                let row = y * self.cols..(y + 1) * self.cols;
+                for cell in self.cells[row][x.min(self.cols)..].iter_mut() {
+                    *cell = " ".to_string();
+                }
+            }
+            // attributes and the others are not modeled
+            _ => {}
+        }
+    }
+
+    // put text from the cursor, clipped at the right edge
+    fn print(&mut self, text: &str) {
+        let (mut x, y) = self.cursor;
+        if self.rows <= y {
+            return;
+        }
+        for g in text.graphemes(true) {
+            let width = g.width();
+            let i = y * self.cols + x;
+            if width == 0 {
+                // joined to the previous cell
+                if let Some(cell) = self.cells[y * self.cols..i]
+                    .iter_mut()
+                    .rev()
+                    .find(|cell| !cell.is_empty())
+                {
+                    cell.push_str(g);
+                }
+                continue;
+            }
+            if self.cols < x + width {
+                break;
+            }
+            self.cells[i] = g.to_string();
+            for cell in self.cells[i + 1..i + width].iter_mut() {
+                cell.clear();
+            }
+            x += width;
+        }
+        self.cursor.0 = x;
+    }
+}
+
+// in-memory terminal to drive the editor with scripted input and inspect the screen.
+// clones share the same terminal, so a clone given to the editor can be inspected.
+#[derive(Clone)]
+pub struct VirtualTerminal {
+    state: Rc<RefCell<VirtualState>>,
+}
+
+impl VirtualTerminal {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(VirtualState::new(cols.into(), rows.into()))),
+        }
+    }
+
+    pub fn push_event(&self, event: Event) {
+        self.state.borrow_mut().input.push_back(Input::Event(event));
+    }
+
+    pub fn push_key(&self, key: Key) {
+        self.push_event(Event::Key(key));
+    }
+
+    pub fn push_keys(&self, keys: &[Key]) {
+        keys.iter().for_each(|key| self.push_key(*key));
+    }
+
+    // type each char of the text, `\n` is Enter
+    pub fn push_text(&self, text: &str) {
+        text.chars().for_each(|c| self.push_key(Key::Char(c)));
+    }
+
+    // resize the terminal after the input pushed so far is read
+    pub fn push_resize(&self, cols: u16, rows: u16) {
+        self.state
+            .borrow_mut()
+            .input
+            .push_back(Input::Resize(cols, rows));
+    }
+
+    // text of each row of the screen
+    pub fn lines(&self) -> Vec<String> {
+        let state = self.state.borrow();
+        state
+            .cells
+            .chunks(state.cols.max(1))
+            .take(state.rows)
+            .map(|row| row.concat())
+            .collect()
+    }
+
+    pub fn line(&self, y: usize) -> String {
+        self.lines().get(y).cloned().unwrap_or_default()
+    }
+
+    // return (x, y)
+    pub fn cursor(&self) -> (usize, usize) {
+        self.state.borrow().cursor
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.state.borrow().cursor_visible
+    }
+}
+
+impl Terminal for VirtualTerminal {
+    fn read_event(&mut self) -> io::Result<Event> {
+        let mut state = self.state.borrow_mut();
+        match state.input.pop_front() {
+            Some(Input::Event(event)) => Ok(event),
+            Some(Input::Resize(cols, rows)) => {
+                // contents are lost like a real terminal may wrap them
+                let input = std::mem::take(&mut state.input);
+                *state = VirtualState::new(cols.into(), rows.into());
+                state.input = input;
+                state.resized = true;
+                Err(io::Error::new(io::ErrorKind::Interrupted, "resized"))
+            }
+            None => {
+                state.closed = true;
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "no more scripted input",
+                ))
+            }
+        }
+    }
+
+    fn take_resized(&mut self) -> bool {
+        std::mem::take(&mut self.state.borrow_mut().resized)
+    }
+
+    fn size(&mut self) -> io::Result<(u16, u16)> {
+        let state = self.state.borrow();
+        Ok((state.cols as u16, state.rows as u16))
+    }
+
+    fn write(&mut self, s: &str) -> io::Result<()> {
+        let mut state = self.state.borrow_mut();
+        if !state.closed {
+            state.write(s);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+// viewport of a buffer on the screen
+#[derive(Debug, Clone, Default)]
+pub struct Window {
+    // index of Editor buffers
+    pub buffer: usize,
+    pub cursor_x: usize,
+    pub cursor_y: usize,
+    pub render_x: usize,
+    pub row_offset: usize,
+    pub col_offset: usize,
+    // screen lines of the row at row_offset scrolled out in soft wrap mode
+    pub wrap_offset: usize,
+    // text area on the screen (0-origin), status bar is drawn below it
+    pub top: usize,
+    pub left: usize,
+    pub rows: usize,
+    pub cols: usize,
+    // width of line numbers at the left of the text area
+    pub gutter: usize,
+}
+
+impl Window {
+    // width of text on the right of the gutter
+    pub fn text_cols(&self) -> usize {
+        self.cols.saturating_sub(self.gutter)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    // windows are stacked top and bottom
+    Horizontal,
+    // windows are placed side by side
+    Vertical,
+}
+
+// tree of windows. leaves are indices of Editor windows.
+#[derive(Debug)]
+pub enum Layout {
+    Window(usize),
+    Split {
+        direction: SplitDirection,
+        // ratio of the first child in the split
+        ratio: f64,
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+// vertical line between side by side windows
+pub struct Separator {
+    pub top: usize,
+    pub left: usize,
+    pub height: usize,
+}
+
+const MIN_RATIO: f64 = 0.1;
+const MAX_RATIO: f64 = 0.9;
+
+impl Layout {
+    // replace window n with a split of n and new_window
+    pub fn split(&mut self, n: usize, new_window: usize, direction: SplitDirection) {
+        match self {
+            Self::Window(i) if *i == n => {
+                *self = Self::Split {
+                    direction,
+                    ratio: 0.5,
+                    first: Box::new(Self::Window(n)),
+                    second: Box::new(Self::Window(new_window)),
+                };
+            }
+            Self::Window(_) => {}
+            Self::Split { first, second, .. } => {
+                first.split(n, new_window, direction);
+                second.split(n, new_window, direction);
+            }
+        }
+    }
+
+    // remove window n and give its area to the sibling.
+    // indices after n are shifted as windows are removed from Vec.
+    // return None if no window is left.
+    pub fn remove(self, n: usize) -> Option<Self> {
+        match self {
+            Self::Window(i) if i == n => None,
+            Self::Window(i) => Some(Self::Window(if n < i { i - 1 } else { i })),
+            Self::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => match (first.remove(n), second.remove(n)) {
+                (Some(first), Some(second)) => Some(Self::Split {
+                    direction,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(layout), None) | (None, Some(layout)) => Some(layout),
+                (None, None) => None,
+            },
+        }
+    }
+
+    // grow window n by delta ratio of the nearest split containing it.
+    // negative delta shrinks the window.
+    // return false if window n is not split.
+    pub fn resize(&mut self, n: usize, delta: f64) -> bool {
+        match self {
+            Self::Window(_) => false,
+            Self::Split {
+                ratio,
+                first,
+                second,
+                ..
+            } => {
+                if first.resize(n, delta) || second.resize(n, delta) {
+                    return true;
+                }
+                if first.contains(n) {
+                    *ratio = (*ratio + delta).clamp(MIN_RATIO, MAX_RATIO);
+                    true
+                } else if second.contains(n) {
+                    *ratio = (*ratio - delta).clamp(MIN_RATIO, MAX_RATIO);
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    pub fn contains(&self, n: usize) -> bool {
+        match self {
+            Self::Window(i) => *i == n,
+            Self::Split { first, second, .. } => first.contains(n) || second.contains(n),
+        }
+    }
+
+    // set position and size of windows in the area, return separators between windows
+    pub fn arrange(
+        &self,
+        top: usize,
+        left: usize,
+        height: usize,
+        width: usize,
+        windows: &mut [Window],
+    ) -> Vec<Separator> {
+        match self {
+            Self::Window(i) => {
+                if let Some(window) = windows.get_mut(*i) {
+                    window.top = top;
+                    window.left = left;
+                    // last row is for status bar
+                    window.rows = height.saturating_sub(1);
+                    window.cols = width;
+                }
+                Vec::new()
+            }
+            Self::Split {
+                direction: SplitDirection::Horizontal,
+                ratio,
+                first,
+                second,
+            } => {
+                // window needs a text row and a status bar
+                let first_height = split_size(height, *ratio, 2);
+                let mut separators = first.arrange(top, left, first_height, width, windows);
+                separators.append(&mut second.arrange(
+                    top + first_height,
+                    left,
+                    height - first_height,
+                    width,
+                    windows,
+                ));
+                separators
+            }
+            Self::Split {
+                direction: SplitDirection::Vertical,
+                ratio,
+                first,
+                second,
+            } => {
+                let available = width.saturating_sub(1);
+                let first_width = split_size(available, *ratio, 1);
+                let mut separators = first.arrange(top, left, height, first_width, windows);
+                separators.push(Separator {
+                    top,
+                    left: left + first_width,
+                    height,
+                });
+                separators.append(&mut second.arrange(
+                    top,
+                    left + first_width + 1,
+                    height,
+                    available - first_width,
+                    windows,
+                ));
+                separators
+            }
+        }
+    }
+}
+
+// size of the first child, both children get at least min if possible
+fn split_size(total: usize, ratio: f64, min: usize) -> usize {
+    if total < min * 2 {
+        total / 2
+    } else {
+        ((total as f64 * ratio).round() as usize).clamp(min, total - min)
+    }
+}
@@ -0,0 +1,121 @@
+use kiroro::keymap::{Command, KeyPress, Keymap, Lookup};
+use kiroro::settings::Settings;
+use kiroro::syntax::Highlight;
+
+fn lookup(keymap: &Keymap, keys: &str) -> Option<Command> {
+    let keys: Vec<KeyPress> = keys
+        .split_whitespace()
+        .map(|name| KeyPress::parse(name).unwrap())
+        .collect();
+    match keymap.lookup(&keys) {
+        Lookup::Command(command) => Some(command),
+        _ => None,
+    }
+}
+
+#[test]
+fn keymap_keeps_valid_lines() {
+    let mut keymap = Keymap::default();
+    let res =
+        keymap.load("ctrl-x ctrl-s = save\nctrl-k = no-such-command\nalt-# = quit # comment\n");
+
+    let e = res.unwrap_err().to_string();
+    assert!(e.contains("line 2"), "{}", e);
+    assert!(!e.contains("line 1") && !e.contains("line 3"), "{}", e);
+    assert_eq!(lookup(&keymap, "ctrl-x ctrl-s"), Some(Command::Save));
+    assert_eq!(lookup(&keymap, "alt-#"), Some(Command::Quit));
+    assert_eq!(lookup(&keymap, "ctrl-k"), None);
+}
+
+#[test]
+fn settings_keep_hash_in_values() {
+    let mut settings = Settings::default();
+    settings
+        .load("# comment\nword_chars = \"_#\" # chars in words\ntab_stop = 8#\n")
+        .unwrap_err();
+
+    assert_eq!(settings.get("word_chars").unwrap(), "_#");
+    // `8#` is not a number
+    assert_eq!(settings.get("tab_stop").unwrap(), "4");
+}
+
+#[test]
+fn settings_set() {
+    let mut settings = Settings::default();
+    settings.set("tab_stop", "8").unwrap();
+    settings.set("expand_tabs", "true").unwrap();
+    settings.set("colors.keyword1", "red").unwrap();
+    settings.set("colors.string", "95").unwrap();
+    settings.set("filetype.rust.tab_stop", "2").unwrap();
+
+    assert_eq!(settings.tab_stop, 8);
+    assert!(settings.expand_tabs);
+    assert_eq!(settings.color(Highlight::Keyword1), 31);
+    assert_eq!(settings.color(Highlight::String), 95);
+    assert_eq!(settings.filetype(Some("rust")).tab_stop, Some(2));
+    assert_eq!(settings.filetype(Some("c")).tab_stop, None);
+
+    // invalid values keep the current ones
+    assert!(settings.set("tab_stop", "0").is_err());
+    assert!(settings.set("tab_stop", "x").is_err());
+    assert!(settings.set("expand_tabs", "yes").is_err());
+    assert!(settings.set("no_such_setting", "1").is_err());
+    assert!(settings.set("colors.keyword1", "no_such_color").is_err());
+    assert_eq!(settings.tab_stop, 8);
+    assert!(settings.expand_tabs);
+}
+
+#[test]
+fn settings_apply() {
+    let mut settings = Settings::default();
+
+    assert_eq!(settings.apply("tab_stop=2"), Ok(None));
+    assert_eq!(settings.apply("expand_tabs"), Ok(None));
+    assert!(settings.expand_tabs);
+    assert_eq!(settings.apply("noexpand_tabs"), Ok(None));
+    assert!(!settings.expand_tabs);
+    // values are shown by name or name with `?`
+    assert_eq!(
+        settings.apply("tab_stop"),
+        Ok(Some("tab_stop=2".to_string()))
+    );
+    assert_eq!(
+        settings.apply("expand_tabs?"),
+        Ok(Some("expand_tabs=false".to_string()))
+    );
+    // `no` works only with switches
+    assert!(settings.apply("notab_stop").is_err());
+    assert!(settings.apply("nosuch").is_err());
+    assert_eq!(settings.tab_stop, 2);
+}
+
+#[test]
+fn settings_load_sections() {
+    let mut settings = Settings::default();
+    settings
+        .load("line_numbers = true\n[colors]\ncomment = green\n[filetype.python]\nexpand_tabs = true\n")
+        .unwrap();
+
+    assert!(settings.line_numbers);
+    assert_eq!(settings.color(Highlight::Comment), 32);
+    assert_eq!(settings.filetype(Some("python")).expand_tabs, Some(true));
+    assert!(settings.to_string().contains("line_numbers=true"));
+}
+
+#[test]
+fn gutter_width() {
+    let mut settings = Settings::default();
+    assert_eq!(settings.gutter_width(100, 40), 0);
+
+    settings.line_numbers = true;
+    assert_eq!(settings.gutter_width(0, 40), 2);
+    assert_eq!(settings.gutter_width(9, 40), 2);
+    assert_eq!(settings.gutter_width(10, 40), 3);
+    assert_eq!(settings.gutter_width(1000, 40), 5);
+    // no gutter in a window without room for text
+    assert_eq!(settings.gutter_width(1000, 5), 0);
+
+    settings.line_numbers = false;
+    settings.relative_numbers = true;
+    assert_eq!(settings.gutter_width(10, 40), 3);
+}
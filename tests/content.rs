@@ -0,0 +1,99 @@
+use kiroro::content::Content;
+
+#[test]
+fn matching_bracket_skips_strings_and_comments() {
+    let text = "fn f(a: &str) {\n    g(\"(\", 0); // )\n    /* } */\n}\n";
+    let mut content = Content::from_text("a.rs".to_string(), text);
+
+    assert_eq!(content.matching_bracket((0, 4)), Some((0, 12)));
+    assert_eq!(content.matching_bracket((0, 12)), Some((0, 4)));
+    assert_eq!(content.matching_bracket((0, 14)), Some((3, 0)));
+    assert_eq!(content.matching_bracket((3, 0)), Some((0, 14)));
+    assert_eq!(content.matching_bracket((1, 5)), Some((1, 12)));
+    // bracket in a string is matched with all brackets
+    assert_eq!(content.matching_bracket((1, 7)), Some((1, 12)));
+    // not on a bracket
+    assert_eq!(content.matching_bracket((0, 0)), None);
+}
+
+#[test]
+fn matching_bracket_after_tabs_and_wide_chars() {
+    let text = "\t\"あ\" (\t)\n";
+    let mut content = Content::from_text("a.rs".to_string(), text);
+
+    assert_eq!(content.matching_bracket((0, 7)), Some((0, 9)));
+}
+
+#[test]
+fn text_range() {
+    let content = Content::from_text("a.txt".to_string(), "ab\ncあd\n");
+
+    assert_eq!(content.text_range((0, 1), (1, 1)), "b\nc");
+    assert_eq!(content.text_range((1, 1), (1, 4)), "あ");
+    // positions out of the text are clamped, not the whole text
+    assert_eq!(content.text_range((2, 0), (2, 0)), "");
+    assert_eq!(content.text_range((1, 2), (5, 0)), "あd\n");
+    assert_eq!(content.text_range((0, 9), (1, 9)), "\ncあd");
+    assert_eq!(content.text_range((1, 0), (0, 0)), "");
+}
+
+fn lines(content: &Content) -> Vec<String> {
+    (0..content.num_rows())
+        .map(|i| content.line(i).unwrap())
+        .collect()
+}
+
+#[test]
+fn edit_rows() {
+    let mut content = Content::from_text("a.txt".to_string(), "ab\nあc\n");
+    assert_eq!(content.num_rows(), 2);
+    assert_eq!(content.line(2), None);
+
+    content.insert_char(1, 3, 'x').unwrap();
+    content.insert_new_line(0, 1).unwrap();
+    assert_eq!(lines(&content), ["a", "b", "あxc"]);
+    content.concatenate_previous_row(1, (1, 0)).unwrap();
+    content.delete_char(1, 0, (1, 3)).unwrap();
+    assert_eq!(lines(&content), ["ab", "xc"]);
+
+    // typing on the row after the last one adds a row
+    content.insert_char(2, 0, 'z').unwrap();
+    assert_eq!(lines(&content), ["ab", "xc", "z"]);
+    // positions inside of a char or out of rows are errors
+    assert!(content.insert_char(0, 5, 'y').is_err());
+    assert!(content.delete_char(5, 0, (5, 0)).is_err());
+}
+
+#[test]
+fn insert_and_delete_text() {
+    let mut content = Content::from_text("a.txt".to_string(), "abc\ndef\n");
+
+    let end = content.insert_text(0, 1, "x\r\ny\nz").unwrap();
+    assert_eq!(end, (2, 1));
+    assert_eq!(lines(&content), ["ax", "y", "zbc", "def"]);
+    content.delete_range((0, 1), (2, 1)).unwrap();
+    assert_eq!(lines(&content), ["abc", "def"]);
+
+    // each edit is undone at once
+    content.undo().unwrap();
+    assert_eq!(lines(&content), ["ax", "y", "zbc", "def"]);
+    assert_eq!(content.undo().unwrap(), Some((0, 1)));
+    assert_eq!(lines(&content), ["abc", "def"]);
+    assert!(!content.is_modified());
+    assert_eq!(content.redo().unwrap(), Some((2, 1)));
+    assert!(content.is_modified());
+}
+
+#[test]
+fn rendered_rows_follow_edits() {
+    let mut content = Content::from_text("a.txt".to_string(), "\tab\nc\n");
+    content.set_tab_stop(8);
+    assert_eq!(content.row_from_index(0).unwrap().render, "        ab");
+    assert_eq!(content.row_from_index(1).unwrap().render, "c");
+
+    content.insert_new_line(0, 0).unwrap();
+    assert_eq!(content.row_from_index(0).unwrap().render, "");
+    assert_eq!(content.row_from_index(1).unwrap().render, "        ab");
+    assert_eq!(content.row_from_index(2).unwrap().render, "c");
+    assert!(content.row_from_index(3).is_none());
+}
@@ -0,0 +1,374 @@
+use std::path::PathBuf;
+use std::{env, fs, io, process};
+
+use kiroro::editor::Editor;
+use kiroro::error::Error;
+use kiroro::keymap::Keymap;
+use kiroro::settings::Settings;
+use kiroro::terminal::VirtualTerminal;
+use termion::event::{Event, Key, MouseButton, MouseEvent};
+
+// 8 rows of text, a status bar and a message bar
+const COLS: u16 = 40;
+const ROWS: u16 = 10;
+
+// file in the temporary directory, unique to the test
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("kiroro-test-{}-{}", process::id(), name))
+}
+
+fn numbered_lines(n: usize) -> String {
+    (0..n).map(|i| format!("line {}\n", i)).collect()
+}
+
+// editor on the terminal with default settings and keymap, not the files of the machine
+fn editor(term: &VirtualTerminal) -> Editor {
+    let mut editor = Editor::with_terminal(Box::new(term.clone())).unwrap();
+    editor.set_settings(Settings::default());
+    editor.set_keymap(Keymap::default());
+    editor
+}
+
+fn run(term: &VirtualTerminal, paths: &[&PathBuf]) {
+    run_editor(editor(term), paths);
+}
+
+// edit the files until the scripted input runs out
+fn run_editor(mut editor: Editor, paths: &[&PathBuf]) {
+    let paths = paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    match editor.run(paths) {
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+        res => panic!("unexpected end of the editor: {:?}", res),
+    }
+}
+
+fn line(term: &VirtualTerminal, y: usize) -> String {
+    term.line(y).trim_end().to_string()
+}
+
+#[test]
+fn typing() {
+    let path = temp_path("typing.txt");
+    let term = VirtualTerminal::new(COLS, ROWS);
+    term.push_text("hello\nworld");
+    run(&term, &[&path]);
+
+    assert_eq!(line(&term, 0), "hello");
+    assert_eq!(line(&term, 1), "world");
+    assert_eq!(line(&term, 2), "~");
+    assert!(line(&term, 8).contains("[modified]"));
+    assert_eq!(term.cursor(), (5, 1));
+    // nothing is written without save
+    assert!(!path.exists());
+}
+
+#[test]
+fn save() {
+    let path = temp_path("save.txt");
+    fs::write(&path, "abc\n").unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    term.push_keys(&[Key::Char('X'), Key::Ctrl('s')]);
+    run(&term, &[&path]);
+
+    let text = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(text, "Xabc\n");
+    assert_eq!(line(&term, 0), "Xabc");
+    assert!(!line(&term, 8).contains("[modified]"));
+}
+
+#[test]
+fn scrolling() {
+    let path = temp_path("scrolling.txt");
+    fs::write(&path, numbered_lines(100)).unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    term.push_keys(&[Key::Down; 20]);
+    run(&term, &[&path]);
+    fs::remove_file(&path).unwrap();
+
+    // the cursor row is at the bottom of the window
+    assert_eq!(line(&term, 0), "line 13");
+    assert_eq!(line(&term, 7), "line 20");
+    assert_eq!(term.cursor(), (0, 7));
+    assert!(line(&term, 8).ends_with("21/100"));
+}
+
+#[test]
+fn resize() {
+    let path = temp_path("resize.txt");
+    fs::write(&path, numbered_lines(100)).unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    term.push_keys(&[Key::Down; 5]);
+    term.push_resize(30, 6);
+    run(&term, &[&path]);
+    fs::remove_file(&path).unwrap();
+
+    // 4 rows of text are left, the cursor row is kept in sight
+    assert_eq!(term.lines().len(), 6);
+    assert!(term.lines().iter().all(|line| line.chars().count() == 30));
+    assert_eq!(line(&term, 0), "line 2");
+    assert_eq!(line(&term, 3), "line 5");
+    assert!(line(&term, 4).ends_with("6/100"));
+    assert_eq!(term.cursor(), (0, 3));
+}
+
+#[test]
+fn close_last_window() {
+    let path = temp_path("close.txt");
+    fs::write(&path, "abc\n").unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    // split, then close the new window, which is the last one
+    term.push_keys(&[Key::Alt('v'), Key::Alt('c'), Key::Char('X')]);
+    run(&term, &[&path]);
+    fs::remove_file(&path).unwrap();
+
+    // one window spans the screen again, without a separator
+    assert_eq!(line(&term, 0), "Xabc");
+    assert!(line(&term, 8).contains("[modified]"));
+    assert!(line(&term, 8).ends_with("1/1"));
+}
+
+#[test]
+fn vim_repeat() {
+    let path = temp_path("repeat.txt");
+    fs::write(&path, "abc\ndef\nghi\njkl\n").unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    // typed text is repeated with the command entering insert mode
+    term.push_text("iX");
+    term.push_key(Key::Esc);
+    term.push_text(".jAY");
+    term.push_key(Key::Esc);
+    term.push_text("j.");
+    // yank is not repeated, `.` deletes a char again
+    term.push_text("j0xyy.");
+    let mut editor = editor(&term);
+    editor.set_vim(true);
+    run_editor(editor, &[&path]);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(line(&term, 0), "XXabc");
+    assert_eq!(line(&term, 1), "defY");
+    assert_eq!(line(&term, 2), "ghiY");
+    assert_eq!(line(&term, 3), "l");
+}
+
+#[test]
+fn word_motion_over_combining_marks() {
+    let path = temp_path("graphemes.txt");
+    fs::write(&path, "cafe\u{301} bar\n").unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    let ctrl_right = Event::Unsupported(b"\x1b[1;5C".to_vec());
+    let ctrl_backspace = Event::Unsupported(b"\x1b[127;5u".to_vec());
+    // ctrl+right stops at the next word, not before the combining mark
+    term.push_event(ctrl_right);
+    term.push_key(Key::Char('Z'));
+    // ctrl+backspace deletes the word with its mark
+    term.push_key(Key::Left);
+    term.push_key(Key::Left);
+    term.push_event(ctrl_backspace);
+    run(&term, &[&path]);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(line(&term, 0), " Zbar");
+}
+
+#[test]
+fn mouse_at_zero() {
+    let path = temp_path("mouse.txt");
+    fs::write(&path, "abc\ndef\n").unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    term.push_key(Key::Down);
+    // X10 reports give 0 for positions out of their range
+    term.push_event(Event::Mouse(MouseEvent::Press(MouseButton::Left, 0, 0)));
+    term.push_event(Event::Mouse(MouseEvent::Press(
+        MouseButton::WheelDown,
+        0,
+        0,
+    )));
+    term.push_event(Event::Mouse(MouseEvent::Release(0, 0)));
+    term.push_key(Key::Char('X'));
+    run(&term, &[&path]);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(line(&term, 0), "Xabc");
+}
+
+#[test]
+fn quit_times_set_at_runtime() {
+    let path = temp_path("quit.txt");
+    // wide enough for the warning
+    let term = VirtualTerminal::new(120, ROWS);
+    // first quit warns of the unsaved change with the default quit_times=2
+    term.push_keys(&[Key::Char('x'), Key::Ctrl('q')]);
+    term.push_key(Key::Alt('x'));
+    term.push_text("quit_times=3\n");
+    // second quit is warned again by the new quit_times
+    term.push_key(Key::Ctrl('q'));
+    run(&term, &[&path]);
+
+    assert!(line(&term, 9).contains("press ctrl-q 1 more times to quit"));
+}
+
+#[test]
+fn zero_rows() {
+    let path = temp_path("zero.txt");
+    let term = VirtualTerminal::new(COLS, 0);
+    term.push_keys(&[Key::Char('x'), Key::Down, Key::Char('\n')]);
+    run(&term, &[&path]);
+
+    assert!(term.lines().is_empty());
+}
+
+#[test]
+fn warn_lossy_open() {
+    let mixed = temp_path("mixed.txt");
+    fs::write(&mixed, "a\r\nb\nc\r\n").unwrap();
+    let latin1 = temp_path("latin1.txt");
+    fs::write(&latin1, b"caf\xe9\n").unwrap();
+    // wide enough for the warning
+    let mixed_term = VirtualTerminal::new(120, ROWS);
+    run(&mixed_term, &[&mixed]);
+    let latin1_term = VirtualTerminal::new(120, ROWS);
+    run(&latin1_term, &[&latin1]);
+    fs::remove_file(&mixed).unwrap();
+    fs::remove_file(&latin1).unwrap();
+
+    assert!(line(&mixed_term, 9).ends_with("mixed line endings, saved as CRLF"));
+    assert!(line(&latin1_term, 9).ends_with("not valid UTF-8, opened as latin-1"));
+    assert_eq!(line(&latin1_term, 0), "café");
+}
+
+#[test]
+fn vim_repeat_as_typed_with_count() {
+    let path = temp_path("repeat-count.txt");
+    fs::write(&path, "abcdef\nb\nc\n").unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    // count of `.` replaces the count of the change
+    term.push_text("x3.");
+    // tab typed with expand_tabs is repeated as spaces
+    term.push_text("jI\t");
+    term.push_key(Key::Esc);
+    term.push_text("j.");
+    // typed text is inserted count times
+    term.push_text("0iz");
+    term.push_key(Key::Esc);
+    term.push_text("2.:w\n");
+    let mut settings = Settings::default();
+    settings.expand_tabs = true;
+    let mut editor = editor(&term);
+    editor.set_settings(settings);
+    editor.set_vim(true);
+    run_editor(editor, &[&path]);
+
+    let text = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(text, "ef\n    b\nzzz    c\n");
+}
+
+#[test]
+fn undo_delete_key() {
+    let path = temp_path("undo-delete.txt");
+    fs::write(&path, "abc\ndef\n").unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    // the cursor is restored before the text deleted by delete key
+    term.push_keys(&[Key::Right, Key::Delete, Key::Delete, Key::Ctrl('z')]);
+    term.push_key(Key::Char('X'));
+    // and at the end of the row joined by delete key
+    term.push_keys(&[Key::End, Key::Delete, Key::Ctrl('z'), Key::Char('Y')]);
+    run(&term, &[&path]);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(line(&term, 0), "aXbcY");
+    assert_eq!(line(&term, 1), "def");
+}
+
+#[test]
+fn vim_buffer_commands() {
+    let first = temp_path("buffers-1.txt");
+    fs::write(&first, "abc\n").unwrap();
+    let second = temp_path("buffers-2.txt");
+    fs::write(&second, "def\n").unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    term.push_text(":b 2\nx:w\n");
+    // the buffer is picked by :ls
+    term.push_text(":ls\n1\nx:w\n");
+    term.push_text(":b 3\n");
+    let mut editor = editor(&term);
+    editor.set_vim(true);
+    run_editor(editor, &[&first, &second]);
+
+    let texts = (
+        fs::read_to_string(&first).unwrap(),
+        fs::read_to_string(&second).unwrap(),
+    );
+    fs::remove_file(&first).unwrap();
+    fs::remove_file(&second).unwrap();
+    assert_eq!(texts, ("bc\n".to_string(), "ef\n".to_string()));
+    assert_eq!(line(&term, 9), "no buffer: 3");
+}
+
+#[test]
+fn line_numbers() {
+    let path = temp_path("numbers.txt");
+    fs::write(&path, numbered_lines(12)).unwrap();
+    let term = VirtualTerminal::new(COLS, ROWS);
+    term.push_keys(&[Key::Down, Key::Down]);
+    let mut numbered = editor(&term);
+    let mut settings = Settings::default();
+    settings.line_numbers = true;
+    numbered.set_settings(settings);
+    run_editor(numbered, &[&path]);
+
+    assert_eq!(line(&term, 0), " 1 line 0");
+    assert_eq!(line(&term, 2), " 3 line 2");
+    assert_eq!(line(&term, 7), " 8 line 7");
+    assert_eq!(term.cursor(), (3, 2));
+
+    // distance from the cursor row, the number of the cursor row itself
+    let term = VirtualTerminal::new(COLS, ROWS);
+    term.push_keys(&[Key::Down, Key::Down]);
+    let mut relative = editor(&term);
+    let mut settings = Settings::default();
+    settings.relative_numbers = true;
+    relative.set_settings(settings);
+    run_editor(relative, &[&path]);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(line(&term, 0), " 2 line 0");
+    assert_eq!(line(&term, 2), " 3 line 2");
+    assert_eq!(line(&term, 3), " 1 line 3");
+    assert_eq!(line(&term, 7), " 5 line 7");
+}
+
+#[test]
+fn open_and_save_errors() {
+    let dir = temp_path("errors");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("missing").join("a.txt");
+    let term = VirtualTerminal::new(120, ROWS);
+    // saving to a missing directory fails, but the editor keeps running
+    term.push_keys(&[Key::Char('x'), Key::Ctrl('s')]);
+    run(&term, &[&dir, &path]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(line(&term, 9).contains("No such file or directory"));
+    assert_eq!(line(&term, 0), "x");
+    assert!(line(&term, 8).contains("[modified]"));
+}
+
+#[test]
+fn open_directory() {
+    let dir = temp_path("open-dir");
+    fs::create_dir_all(&dir).unwrap();
+    let term = VirtualTerminal::new(120, ROWS);
+    term.push_text("ok");
+    run(&term, &[&dir]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    // the error is shown and an empty buffer is edited instead
+    assert!(line(&term, 9).starts_with(&format!("{}: ", dir.display())));
+    assert_eq!(line(&term, 0), "ok");
+}
@@ -0,0 +1,85 @@
+use kiroro::content::Content;
+use kiroro::encoding::{Encoding, LineEnding};
+
+// read bytes of a file and write them back
+fn round_trip(bytes: &[u8]) -> Vec<u8> {
+    let content = Content::from_bytes("a.txt".to_string(), bytes);
+    let mut out = Vec::new();
+    let written = content.write_to(&mut out).unwrap();
+    assert_eq!(written, out.len());
+    out
+}
+
+#[test]
+fn detect_line_ending() {
+    assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::CrLf);
+    assert_eq!(LineEnding::detect("a\nb\r\n"), LineEnding::Lf);
+    assert_eq!(LineEnding::detect("a\rb"), LineEnding::Lf);
+    assert!(!LineEnding::is_mixed("a\r\nb\r\nc"));
+    assert!(!LineEnding::is_mixed("a\nb\n"));
+    assert!(LineEnding::is_mixed("a\r\nb\n"));
+    assert!(LineEnding::is_mixed("a\nb\r\n"));
+}
+
+#[test]
+fn decode() {
+    assert_eq!(
+        Encoding::decode("aé".as_bytes()),
+        ("aé".to_string(), Encoding::Utf8, false)
+    );
+    assert_eq!(
+        Encoding::decode(b"\xef\xbb\xbfa"),
+        ("a".to_string(), Encoding::Utf8, true)
+    );
+    assert_eq!(
+        Encoding::decode(b"\xff\xfea\x00\xe9\x00"),
+        ("aé".to_string(), Encoding::Utf16Le, true)
+    );
+    assert_eq!(
+        Encoding::decode(b"\xfe\xff\x00a\x00\xe9"),
+        ("aé".to_string(), Encoding::Utf16Be, true)
+    );
+    // invalid UTF-8 falls back to latin-1
+    assert_eq!(
+        Encoding::decode(b"caf\xe9"),
+        ("café".to_string(), Encoding::Latin1, false)
+    );
+}
+
+#[test]
+fn encode() {
+    let mut out = Vec::new();
+    Encoding::Utf16Be.encode("aé", &mut out).unwrap();
+    assert_eq!(out, b"\x00a\x00\xe9");
+
+    let mut out = Vec::new();
+    Encoding::Latin1.encode("é", &mut out).unwrap();
+    assert_eq!(out, b"\xe9");
+    assert!(Encoding::Latin1.encode("あ", &mut Vec::new()).is_err());
+}
+
+#[test]
+fn files_round_trip() {
+    let files: [&[u8]; 7] = [
+        b"a\nb\n",
+        b"a\r\nb\r\n",
+        // without final newline
+        b"a\r\nb",
+        b"",
+        b"\xef\xbb\xbfa\n",
+        b"\xff\xfea\x00\n\x00",
+        b"caf\xe9\n\xff",
+    ];
+    for bytes in files {
+        assert_eq!(round_trip(bytes), bytes);
+    }
+}
+
+#[test]
+fn mixed_line_endings_are_converted() {
+    let content = Content::from_bytes("a.txt".to_string(), b"a\r\nb\nc\r\n");
+    assert!(content.mixed_line_ending);
+    assert_eq!(content.line(1).unwrap(), "b");
+
+    assert_eq!(round_trip(b"a\r\nb\nc\r\n"), b"a\r\nb\r\nc\r\n");
+}
@@ -0,0 +1,15 @@
+use std::error::Error as _;
+use std::io;
+
+use kiroro::error::Error;
+
+#[test]
+fn display_and_source() {
+    let e = Error::from(io::Error::new(io::ErrorKind::NotFound, "no file"));
+    assert_eq!(e.to_string(), "no file");
+    assert!(e.source().is_some());
+
+    let e = Error::Config("invalid tab_stop".to_string());
+    assert_eq!(e.to_string(), "invalid tab_stop");
+    assert!(e.source().is_none());
+}
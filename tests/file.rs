@@ -0,0 +1,83 @@
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::PathBuf;
+use std::{env, fs, io, process};
+
+use kiroro::file::write_atomic;
+
+// directory in the temporary directory, unique to the test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("kiroro-test-{}-{}", process::id(), name));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn file_names(dir: &PathBuf) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn write_new_and_existing_files() {
+    let dir = temp_dir("write-atomic");
+    let path = dir.join("a.txt");
+
+    let written = write_atomic(&path, false, |out| out.write_all(b"abc").map(|_| 3)).unwrap();
+    assert_eq!(written, 3);
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+    write_atomic(&path, false, |out| out.write_all(b"de").map(|_| 2)).unwrap();
+
+    let text = fs::read_to_string(&path).unwrap();
+    let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+    let names = file_names(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(text, "de");
+    // permissions are kept, no temporary file is left
+    assert_eq!(mode, 0o640);
+    assert_eq!(names, ["a.txt"]);
+}
+
+#[test]
+fn backup_and_symlink() {
+    let dir = temp_dir("write-backup");
+    let path = dir.join("a.txt");
+    let link = dir.join("link.txt");
+    fs::write(&path, "old").unwrap();
+    symlink(&path, &link).unwrap();
+
+    write_atomic(&link, true, |out| out.write_all(b"new").map(|_| 3)).unwrap();
+
+    let text = fs::read_to_string(&path).unwrap();
+    let backup = fs::read_to_string(dir.join("a.txt~")).unwrap();
+    let is_link = fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink();
+    fs::remove_dir_all(&dir).unwrap();
+    // the file behind the symlink is replaced
+    assert_eq!(text, "new");
+    assert_eq!(backup, "old");
+    assert!(is_link);
+}
+
+#[test]
+fn failed_write_keeps_the_file() {
+    let dir = temp_dir("write-failed");
+    let path = dir.join("a.txt");
+    fs::write(&path, "old").unwrap();
+
+    let res = write_atomic(&path, true, |out| {
+        out.write_all(b"partial")?;
+        Err(io::Error::other("failed"))
+    });
+
+    let text = fs::read_to_string(&path).unwrap();
+    let names = file_names(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(res.is_err());
+    assert_eq!(text, "old");
+    assert_eq!(names, ["a.txt"]);
+}
@@ -0,0 +1,139 @@
+use kiroro::history::{History, Operation};
+
+fn insert(row: usize, col: usize, text: &str) -> Operation {
+    Operation::Insert {
+        row,
+        col,
+        text: text.to_string(),
+    }
+}
+
+fn delete(row: usize, col: usize, text: &str, cursor: usize) -> Operation {
+    Operation::Delete {
+        row,
+        col,
+        text: text.to_string(),
+        cursor: (row, cursor),
+    }
+}
+
+// texts of the operations in an undo step
+fn texts(ops: &[Operation]) -> Vec<String> {
+    ops.iter()
+        .map(|op| match op {
+            Operation::Insert { text, .. } | Operation::Delete { text, .. } => text.clone(),
+            op => format!("{:?}", op),
+        })
+        .collect()
+}
+
+#[test]
+fn typing_is_merged() {
+    let mut history = History::default();
+    history.record(insert(0, 0, "a"));
+    history.record(insert(0, 1, "b"));
+    // not continuing the typing
+    history.record(insert(1, 0, "c"));
+
+    assert_eq!(texts(&history.undo().unwrap()), ["c"]);
+    assert_eq!(texts(&history.undo().unwrap()), ["ab"]);
+    assert!(history.undo().is_none());
+}
+
+#[test]
+fn deletions_are_merged() {
+    let mut history = History::default();
+    // backspace from col 3
+    history.record(delete(0, 2, "c", 3));
+    history.record(delete(0, 1, "b", 2));
+    history.seal();
+    // delete key at col 0
+    history.record(delete(0, 0, "x", 0));
+    history.record(delete(0, 0, "y", 0));
+
+    let ops = history.undo().unwrap();
+    assert_eq!(texts(&ops), ["xy"]);
+    assert_eq!(ops[0].cursor_before(), (0, 0));
+    let ops = history.undo().unwrap();
+    assert_eq!(texts(&ops), ["bc"]);
+    assert_eq!(ops[0].cursor_before(), (0, 3));
+    assert_eq!(ops[0].cursor_after(), (0, 1));
+}
+
+#[test]
+fn seal_and_groups() {
+    let mut history = History::default();
+    history.record(insert(0, 0, "a"));
+    history.seal();
+    history.record(insert(0, 1, "b"));
+    history.begin_group();
+    history.record(insert(1, 0, "c"));
+    history.record(Operation::SplitLine { row: 1, col: 1 });
+    history.end_group();
+    history.record(insert(3, 0, "d"));
+
+    assert_eq!(texts(&history.undo().unwrap()), ["d"]);
+    assert_eq!(texts(&history.undo().unwrap()).len(), 2);
+    assert_eq!(texts(&history.undo().unwrap()), ["b"]);
+    assert_eq!(texts(&history.undo().unwrap()), ["a"]);
+}
+
+#[test]
+fn redo_and_new_edits() {
+    let mut history = History::default();
+    history.record(insert(0, 0, "a"));
+    history.seal();
+    history.record(insert(0, 1, "b"));
+    history.undo();
+
+    assert_eq!(texts(&history.redo().unwrap()), ["b"]);
+    assert!(history.redo().is_none());
+    history.undo();
+    // new edit drops undone steps
+    history.record(insert(0, 1, "c"));
+    assert!(history.redo().is_none());
+    assert_eq!(texts(&history.undo().unwrap()), ["c"]);
+}
+
+#[test]
+fn saved_state() {
+    let mut history = History::default();
+    assert!(!history.is_modified());
+    history.record(insert(0, 0, "a"));
+    assert!(history.is_modified());
+    history.mark_saved();
+    assert!(!history.is_modified());
+
+    // typing after save is a new step
+    history.record(insert(0, 1, "b"));
+    assert!(history.is_modified());
+    history.undo();
+    assert!(!history.is_modified());
+    history.undo();
+    assert!(history.is_modified());
+    history.redo();
+    assert!(!history.is_modified());
+
+    // saved state is unreachable after undone steps are dropped
+    history.undo();
+    history.record(insert(0, 0, "c"));
+    history.undo();
+    assert!(history.is_modified());
+    history.redo();
+    assert!(history.is_modified());
+}
+
+#[test]
+fn inverse() {
+    let ops = [
+        insert(0, 1, "ab"),
+        delete(0, 1, "ab", 3),
+        Operation::SplitLine { row: 0, col: 1 },
+        Operation::InsertLine { row: 2 },
+    ];
+    for op in ops {
+        let inverse = op.inverse().inverse();
+        assert_eq!(format!("{:?}", inverse), format!("{:?}", op));
+        assert_eq!(op.inverse().cursor_before(), op.cursor_after());
+    }
+}
@@ -0,0 +1,83 @@
+use kiroro::screen::{Screen, Style};
+
+const HIDE: &str = "\x1b[?25l";
+const SHOW: &str = "\x1b[?25h";
+
+#[test]
+fn render_only_changes() {
+    let mut screen = Screen::new(4, 2);
+
+    // the first frame erases the screen and writes every cell
+    assert_eq!(
+        screen.render().unwrap(),
+        format!("{HIDE}\x1b[2J\x1b[1;1H\x1b[0;39m    \x1b[2;1H    \x1b[m\x1b[1;1H{SHOW}")
+    );
+    assert_eq!(screen.render(), None);
+
+    screen.put(1, 0, "ab", Style::fg(31));
+    assert_eq!(
+        screen.render().unwrap(),
+        format!("{HIDE}\x1b[1;2H\x1b[0;31mab\x1b[m\x1b[1;1H{SHOW}")
+    );
+
+    // the same frame again writes nothing
+    screen.clear();
+    screen.put(1, 0, "ab", Style::fg(31));
+    assert_eq!(screen.render(), None);
+
+    // only the cursor moves
+    screen.set_cursor(2, 1);
+    assert_eq!(screen.render().unwrap(), format!("\x1b[2;3H{SHOW}"));
+    screen.hide_cursor();
+    assert_eq!(screen.render().unwrap(), HIDE);
+}
+
+#[test]
+fn render_wide_chars() {
+    let mut screen = Screen::new(4, 1);
+    screen.render();
+
+    assert_eq!(screen.put(0, 0, "aあ", Style::default()), 3);
+    assert_eq!(
+        screen.render().unwrap(),
+        format!("{HIDE}\x1b[1;1H\x1b[0;39maあ\x1b[m\x1b[1;1H{SHOW}")
+    );
+
+    // the right half is overwritten, the left half is written again
+    screen.clear();
+    screen.put(0, 0, "a", Style::default());
+    assert_eq!(
+        screen.render().unwrap(),
+        format!("{HIDE}\x1b[1;2H\x1b[0;39m  \x1b[m\x1b[1;1H{SHOW}")
+    );
+
+    // a wide char which doesn't fit is clipped, zero width chars are joined
+    screen.clear();
+    assert_eq!(screen.put(2, 0, "e\u{301}あ", Style::default()), 1);
+    assert_eq!(
+        screen.render().unwrap(),
+        format!("{HIDE}\x1b[1;1H\x1b[0;39m \x1b[1;3He\u{301}\x1b[m\x1b[1;1H{SHOW}")
+    );
+}
+
+#[test]
+fn invalidate_and_resize_redraw_everything() {
+    let mut screen = Screen::new(2, 1);
+    screen.put(0, 0, "ab", Style::default());
+    screen.render();
+
+    screen.invalidate();
+    assert_eq!(
+        screen.render().unwrap(),
+        format!("{HIDE}\x1b[2J\x1b[1;1H\x1b[0;39mab\x1b[m\x1b[1;1H{SHOW}")
+    );
+
+    screen.resize(3, 1);
+    assert_eq!(
+        screen.render().unwrap(),
+        format!("{HIDE}\x1b[2J\x1b[1;1H\x1b[0;39m   \x1b[m\x1b[1;1H{SHOW}")
+    );
+    // out of the screen
+    assert_eq!(screen.put(0, 1, "a", Style::default()), 0);
+    assert_eq!(screen.render(), None);
+}
@@ -0,0 +1,35 @@
+use kiroro::content::Content;
+use kiroro::search::{Pattern, SearchHighlight, SearchOptions};
+
+fn status(content: &Content, query: &str, pos: (usize, usize)) -> String {
+    let pattern = Pattern::new(query, &SearchOptions::default()).unwrap();
+    let count = content.count_matches(&pattern, pos.0, pos.1);
+    SearchHighlight {
+        pattern,
+        current: None,
+        count,
+    }
+    .status()
+}
+
+#[test]
+fn count_matches() {
+    let content = Content::from_text("a.txt".to_string(), "ab ab\nb\nab\n");
+
+    assert_eq!(status(&content, "ab", (0, 3)), "match 2 of 3");
+    assert_eq!(status(&content, "ab", (1, 0)), "3 matches");
+    assert_eq!(status(&content, "x", (0, 0)), "no match");
+}
+
+#[test]
+fn count_matches_stops_at_limit() {
+    let text = "a a a a a\n".repeat(1000);
+    let content = Content::from_text("a.txt".to_string(), &text);
+    assert_eq!(status(&content, "a", (0, 2)), "match 2 of 1000+");
+    assert_eq!(status(&content, "a", (999, 0)), "1000+ matches");
+
+    // rows after the limit are not scanned
+    let text = format!("{}a\n", "b\n".repeat(100_000));
+    let content = Content::from_text("a.txt".to_string(), &text);
+    assert_eq!(status(&content, "a", (100_000, 0)), "matches not counted");
+}
@@ -0,0 +1,90 @@
+use kiroro::content::Content;
+use kiroro::syntax::{Highlight, Syntax};
+
+use Highlight::*;
+
+// highlights of rows, each row continues multiline comment state of the previous row
+fn highlight_rows(syntax: &Syntax, rows: &[&str]) -> Vec<(Vec<Highlight>, bool)> {
+    let mut open_comment = false;
+    rows.iter()
+        .map(|row| {
+            let (hl, open) = syntax.highlight(row, open_comment);
+            open_comment = open;
+            (hl, open)
+        })
+        .collect()
+}
+
+#[test]
+fn select_by_filename() {
+    assert_eq!(Syntax::select("src/main.rs").unwrap().filetype, "rust");
+    assert_eq!(Syntax::select("a.h").unwrap().filetype, "c");
+    assert!(Syntax::select("rs").is_none());
+    assert!(Syntax::select("a.txt").is_none());
+}
+
+#[test]
+fn highlight_row() {
+    let rust = Syntax::select("a.rs").unwrap();
+    let (hl, open) = rust.highlight("let s = \"a\\\"\"; // 1", false);
+
+    assert_eq!(&hl[..4], [Keyword1, Keyword1, Keyword1, Normal]);
+    assert_eq!(&hl[8..13], [String; 5]);
+    assert_eq!(hl[13], Normal);
+    assert_eq!(&hl[15..], [Comment; 4]);
+    assert!(!open);
+
+    let (hl, _) = rust.highlight("x1 = 12.5 + u8", false);
+    assert_eq!(&hl[..2], [Normal, Normal]);
+    assert_eq!(&hl[5..9], [Number; 4]);
+    assert_eq!(&hl[12..], [Keyword2; 2]);
+}
+
+#[test]
+fn multiline_comment_across_rows() {
+    let rust = Syntax::select("a.rs").unwrap();
+    let rows = highlight_rows(rust, &["a /* b", "c", "d */ e", "\"/*\" f"]);
+
+    assert_eq!(
+        rows[0].0,
+        [
+            Normal,
+            Normal,
+            MultilineComment,
+            MultilineComment,
+            MultilineComment,
+            MultilineComment
+        ]
+    );
+    assert!(rows[0].1);
+    assert_eq!(rows[1], (vec![MultilineComment], true));
+    assert_eq!(&rows[2].0[..4], [MultilineComment; 4]);
+    assert_eq!(&rows[2].0[4..], [Normal, Normal]);
+    assert!(!rows[2].1);
+    // comment start in a string doesn't open comment
+    assert!(!rows[3].1);
+}
+
+#[test]
+fn multiline_comment_in_content() {
+    let text = "/*\nfn\n*/\nfn\n";
+    // rows before are highlighted to find the state, without being cached
+    let mut content = Content::from_text("a.rs".to_string(), text);
+    assert_eq!(content.row_from_index(3).unwrap().hl[0], Keyword1);
+    assert_eq!(content.row_from_index(1).unwrap().hl[0], MultilineComment);
+
+    let mut content = Content::from_text("a.rs".to_string(), text);
+    let kinds: Vec<Highlight> = (0..4)
+        .map(|i| content.row_from_index(i).unwrap().hl[0])
+        .collect();
+
+    assert_eq!(
+        kinds,
+        [
+            MultilineComment,
+            MultilineComment,
+            MultilineComment,
+            Keyword1
+        ]
+    );
+}
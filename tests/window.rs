@@ -0,0 +1,86 @@
+use kiroro::window::{Layout, Separator, SplitDirection, Window};
+
+// (top, left, rows, cols) of each window
+fn areas(
+    layout: &Layout,
+    height: usize,
+    width: usize,
+    n: usize,
+) -> Vec<(usize, usize, usize, usize)> {
+    let mut windows = vec![Window::default(); n];
+    layout.arrange(0, 0, height, width, &mut windows);
+    windows
+        .iter()
+        .map(|w| (w.top, w.left, w.rows, w.cols))
+        .collect()
+}
+
+fn separators(
+    layout: &Layout,
+    height: usize,
+    width: usize,
+    n: usize,
+) -> Vec<(usize, usize, usize)> {
+    let mut windows = vec![Window::default(); n];
+    layout
+        .arrange(0, 0, height, width, &mut windows)
+        .iter()
+        .map(|Separator { top, left, height }| (*top, *left, *height))
+        .collect()
+}
+
+#[test]
+fn split_and_arrange() {
+    let mut layout = Layout::Window(0);
+    layout.split(0, 1, SplitDirection::Horizontal);
+    // each window has a status bar at the bottom
+    assert_eq!(areas(&layout, 20, 80, 2), [(0, 0, 9, 80), (10, 0, 9, 80)]);
+
+    layout.split(1, 2, SplitDirection::Vertical);
+    assert_eq!(
+        areas(&layout, 20, 81, 3),
+        [(0, 0, 9, 81), (10, 0, 9, 40), (10, 41, 9, 40)]
+    );
+    assert_eq!(separators(&layout, 20, 81, 3), [(10, 40, 10)]);
+    assert!(layout.contains(2));
+    assert!(!layout.contains(3));
+}
+
+#[test]
+fn small_area() {
+    let mut layout = Layout::Window(0);
+    layout.split(0, 1, SplitDirection::Horizontal);
+
+    // both windows get a text row and a status bar if possible
+    layout.resize(0, -1.0);
+    assert_eq!(areas(&layout, 5, 10, 2), [(0, 0, 1, 10), (2, 0, 2, 10)]);
+    assert_eq!(areas(&layout, 3, 10, 2), [(0, 0, 0, 10), (1, 0, 1, 10)]);
+    assert_eq!(areas(&layout, 0, 0, 2), [(0, 0, 0, 0), (0, 0, 0, 0)]);
+}
+
+#[test]
+fn resize() {
+    let mut layout = Layout::Window(0);
+    assert!(!layout.resize(0, 0.1));
+
+    layout.split(0, 1, SplitDirection::Vertical);
+    assert!(layout.resize(1, 0.2));
+    assert_eq!(areas(&layout, 10, 101, 2), [(0, 0, 9, 30), (0, 31, 9, 70)]);
+    // ratio is limited so that windows don't disappear
+    assert!(layout.resize(0, 1.0));
+    assert_eq!(areas(&layout, 10, 101, 2), [(0, 0, 9, 90), (0, 91, 9, 10)]);
+}
+
+#[test]
+fn remove() {
+    let mut layout = Layout::Window(0);
+    layout.split(0, 1, SplitDirection::Horizontal);
+    layout.split(0, 2, SplitDirection::Vertical);
+
+    // the sibling (2, shifted to 1) takes the area
+    let layout = layout.remove(0).unwrap();
+    assert_eq!(areas(&layout, 20, 80, 2), [(10, 0, 9, 80), (0, 0, 9, 80)]);
+    let layout = layout.remove(1).unwrap();
+    assert!(matches!(layout, Layout::Window(0)));
+    assert!(layout.remove(0).is_none());
+}